@@ -0,0 +1,375 @@
+
+use DebugClient;
+use Error as ClientError;
+use serde;
+use serde_json::Value as JsonValue;
+
+/// A dummy type for commands that return nothing
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Nothing {
+}
+
+fn deserialize_unit_enum<'de, D: serde::Deserializer<'de>>(_d: D) -> Result<(), D::Error> {
+    Ok(())
+}
+
+pub mod Tree {
+    #[allow(unused_imports)] use serde_json::Value as JsonValue;
+    #[allow(unused_imports)] use super::Nothing;
+    use super::*;
+    /// Unique node identifier.
+    #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    #[serde(transparent)]
+    pub struct NodeId(pub i64);
+    impl ::std::fmt::Display for NodeId {
+        fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+            self.0.fmt(f)
+        }
+    }
+    impl From<i64> for NodeId {
+        fn from(v: i64) -> Self {
+            NodeId(v)
+        }
+    }
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+    #[serde(transparent)]
+    pub struct Label(pub String);
+    impl ::std::fmt::Display for Label {
+        fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+            self.0.fmt(f)
+        }
+    }
+    impl From<String> for Label {
+        fn from(v: String) -> Self {
+            Label(v)
+        }
+    }
+    #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, PartialOrd)]
+    #[serde(transparent)]
+    pub struct Timestamp(pub f64);
+    impl ::std::fmt::Display for Timestamp {
+        fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+            self.0.fmt(f)
+        }
+    }
+    impl From<f64> for Timestamp {
+        fn from(v: f64) -> Self {
+            Timestamp(v)
+        }
+    }
+    pub type Point = Vec<f64>;
+    /// Traversal mode.
+        #[derive(Deserialize, Debug, Serialize, Clone, PartialEq, Eq, Hash)]
+        pub enum Mode {
+            #[serde(rename = "fast-mode")]
+            _fast_mode,
+            #[serde(rename = "slow")]
+            _slow,
+        }
+    /// A node that refers to itself.
+    #[derive(Deserialize, Debug, Serialize, Clone, PartialEq)]
+    pub struct Node {
+        #[serde(rename = "nodeId")]
+        pub _nodeId: NodeId,
+        #[serde(rename = "parent")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub _parent: Option<Box<Node>>,
+        #[serde(rename = "children")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub _children: Option<Vec<Node>>,
+        #[serde(rename = "firstChild")]
+        pub _firstChild: Box<Node>,
+    }
+    #[derive(Deserialize, Debug, Serialize, Clone, PartialEq, Default)]
+    pub struct Options {
+        #[serde(rename = "depth")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub _depth: Option<i64>,
+        #[serde(rename = "mode")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub _mode: Option<Mode>,
+    }
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    pub struct ReturnType_getNode {
+        #[serde(rename = "node")]
+        pub node: Node,
+        #[serde(rename = "timestamp")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub timestamp: Option<Timestamp>,
+    }
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    pub struct Request_getNode {
+        #[serde(rename = "nodeId")]
+        pub _nodeId: NodeId,
+        /// How to "walk" the tree.
+        #[serde(rename = "mode")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub _mode: Option<Mode>,
+    }
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+    pub struct Request_setOptions {
+        #[serde(rename = "options")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub _options: Option<Options>,
+    }
+    pub trait TreeApi {
+        /// Enables events.
+        fn enable(&mut self) -> Result<Nothing, ClientError>;
+        fn getNode(&mut self, _nodeId: NodeId, _mode: Option<Mode>) -> Result<ReturnType_getNode, ClientError>;
+        fn setOptions(&mut self, _options: Option<Options>) -> Result<Nothing, ClientError>;
+    }
+    impl TreeApi for DebugClient {
+        fn enable(&mut self) -> Result<Nothing, ClientError> {
+            self.call("Tree.enable", Nothing {})
+        }
+        fn getNode(&mut self, _nodeId: NodeId, _mode: Option<Mode>) -> Result<ReturnType_getNode, ClientError> {
+            self.call("Tree.getNode", Request_getNode {_nodeId,_mode})
+        }
+        fn setOptions(&mut self, _options: Option<Options>) -> Result<Nothing, ClientError> {
+            self.call("Tree.setOptions", Request_setOptions {_options})
+        }
+    }
+    /// Commands accepted by the Tree domain
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    #[serde(tag = "method", content = "params")]
+    pub enum Command {
+        /// Enables events.
+        #[serde(rename = "Tree.enable")]
+        enable(Nothing),
+        #[serde(rename = "Tree.getNode")]
+        getNode(Request_getNode),
+        #[serde(rename = "Tree.setOptions")]
+        setOptions(Request_setOptions),
+    }
+    impl Command {
+        /// The protocol method name of this command
+        pub fn method_name(&self) -> &'static str {
+            match *self {
+                Command::enable(_) => "Tree.enable",
+                Command::getNode(_) => "Tree.getNode",
+                Command::setOptions(_) => "Tree.setOptions",
+            }
+        }
+        /// Parse the result of this command into the matching ReturnType variant
+        pub fn parse_result(&self, v: JsonValue) -> Result<ReturnType, ::serde_json::Error> {
+            match *self {
+                Command::enable(_) => ::serde_json::from_value(v).map(ReturnType::enable),
+                Command::getNode(_) => ::serde_json::from_value(v).map(ReturnType::getNode),
+                Command::setOptions(_) => ::serde_json::from_value(v).map(ReturnType::setOptions),
+            }
+        }
+    }
+    /// Results of the commands in the Tree domain
+    #[derive(Serialize, Debug, Clone, PartialEq)]
+    #[serde(untagged)]
+    pub enum ReturnType {
+        enable(Nothing),
+        getNode(ReturnType_getNode),
+        setOptions(Nothing),
+    }
+    /// Events emitted by the Tree domain
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    #[serde(tag = "method", content = "params")]
+    pub enum Event {
+        /// Fired without parameters.
+        #[serde(rename = "Tree.ready")]
+        #[serde(deserialize_with="super::deserialize_unit_enum")]
+        ready,
+        #[serde(rename = "Tree.nodeChanged")]
+        nodeChanged {
+            #[serde(rename = "node")]
+            node: Node,
+            #[serde(rename = "type")]
+            _type: String,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            #[serde(rename = "label")]
+            label: Option<Label>,
+        },
+    }
+    impl Event {
+        /// The protocol method name of this event
+        pub fn method_name(&self) -> &'static str {
+            match *self {
+                Event::ready => "Tree.ready",
+                Event::nodeChanged { .. } => "Tree.nodeChanged",
+            }
+        }
+    }
+} // Tree
+pub use self::Tree::TreeApi;
+pub mod Other {
+    #[allow(unused_imports)] use serde_json::Value as JsonValue;
+    #[allow(unused_imports)] use super::Nothing;
+    use super::*;
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    pub struct ReturnType_describe {
+        #[serde(rename = "labels")]
+        pub labels: Vec<super::Tree::Label>,
+    }
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    pub struct Request_describe {
+        #[serde(rename = "nodeIds")]
+        pub _nodeIds: Vec<super::Tree::NodeId>,
+    }
+    pub trait OtherApi {
+        fn describe(&mut self, _nodeIds: Vec<super::Tree::NodeId>) -> Result<ReturnType_describe, ClientError>;
+    }
+    impl OtherApi for DebugClient {
+        fn describe(&mut self, _nodeIds: Vec<super::Tree::NodeId>) -> Result<ReturnType_describe, ClientError> {
+            self.call("Other.describe", Request_describe {_nodeIds})
+        }
+    }
+    /// Commands accepted by the Other domain
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    #[serde(tag = "method", content = "params")]
+    pub enum Command {
+        #[serde(rename = "Other.describe")]
+        describe(Request_describe),
+    }
+    impl Command {
+        /// The protocol method name of this command
+        pub fn method_name(&self) -> &'static str {
+            match *self {
+                Command::describe(_) => "Other.describe",
+            }
+        }
+        /// Parse the result of this command into the matching ReturnType variant
+        pub fn parse_result(&self, v: JsonValue) -> Result<ReturnType, ::serde_json::Error> {
+            match *self {
+                Command::describe(_) => ::serde_json::from_value(v).map(ReturnType::describe),
+            }
+        }
+    }
+    /// Results of the commands in the Other domain
+    #[derive(Serialize, Debug, Clone, PartialEq)]
+    #[serde(untagged)]
+    pub enum ReturnType {
+        describe(ReturnType_describe),
+    }
+} // Other
+pub use self::Other::OtherApi;
+/// A command accepted by the browser, grouped by protocol domain
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Command {
+    Tree(Tree::Command),
+    Other(Other::Command),
+    /// A message for a domain that is not in the protocol spec, e.g. from a protocol
+    /// extension. Holds the whole message.
+    Unknown(JsonValue),
+}
+impl Command {
+    /// The protocol domain, e.g. `Page`. Empty for Unknown, see unknown_method.
+    pub fn domain(&self) -> &'static str {
+        match *self {
+            Command::Tree(_) => "Tree",
+            Command::Other(_) => "Other",
+            Command::Unknown(_) => "",
+        }
+    }
+    /// The protocol method name, e.g. `Page.navigate`. Empty for Unknown, see
+    /// unknown_method.
+    pub fn method_name(&self) -> &'static str {
+        match *self {
+            Command::Tree(ref v) => v.method_name(),
+            Command::Other(ref v) => v.method_name(),
+            Command::Unknown(_) => "",
+        }
+    }
+    /// The method name of an Unknown message, e.g. `Foo.bar`
+    pub fn unknown_method(&self) -> Option<&str> {
+        match *self {
+            Command::Unknown(ref v) => v["method"].as_str(),
+            _ => None,
+        }
+    }
+}
+impl<'de> serde::Deserialize<'de> for Command {
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+        let mut v = JsonValue::deserialize(d)?;
+        // params can be omitted when empty, but the domain enums expect it
+        if let Some(obj) = v.as_object_mut() {
+            obj.entry("params").or_insert_with(|| JsonValue::Object(Default::default()));
+        }
+        let domain = match v.get("method").and_then(JsonValue::as_str) {
+            Some(method) => method.split('.').next().unwrap_or("").to_string(),
+            None => return Err(D::Error::missing_field("method")),
+        };
+        match domain.as_str() {
+            "Tree" => <Tree::Command as serde::Deserialize>::deserialize(v).map(Command::Tree).map_err(D::Error::custom),
+            "Other" => <Other::Command as serde::Deserialize>::deserialize(v).map(Command::Other).map_err(D::Error::custom),
+            _ => Ok(Command::Unknown(v)),
+        }
+    }
+}
+/// The result of a Command, grouped by protocol domain
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum ReturnType {
+    Tree(Tree::ReturnType),
+    Other(Other::ReturnType),
+    /// The result of an Unknown command
+    Unknown(JsonValue),
+}
+impl Command {
+    /// Parse the result of this command into the matching ReturnType variant
+    pub fn parse_result(&self, v: JsonValue) -> Result<ReturnType, ::serde_json::Error> {
+        match *self {
+            Command::Tree(ref cmd) => cmd.parse_result(v).map(ReturnType::Tree),
+            Command::Other(ref cmd) => cmd.parse_result(v).map(ReturnType::Other),
+            Command::Unknown(_) => Ok(ReturnType::Unknown(v)),
+        }
+    }
+}
+/// An event emitted by the browser, grouped by protocol domain
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Event {
+    Tree(Tree::Event),
+    /// A message for a domain that is not in the protocol spec, e.g. from a protocol
+    /// extension. Holds the whole message.
+    Unknown(JsonValue),
+}
+impl Event {
+    /// The protocol domain, e.g. `Page`. Empty for Unknown, see unknown_method.
+    pub fn domain(&self) -> &'static str {
+        match *self {
+            Event::Tree(_) => "Tree",
+            Event::Unknown(_) => "",
+        }
+    }
+    /// The protocol method name, e.g. `Page.navigate`. Empty for Unknown, see
+    /// unknown_method.
+    pub fn method_name(&self) -> &'static str {
+        match *self {
+            Event::Tree(ref v) => v.method_name(),
+            Event::Unknown(_) => "",
+        }
+    }
+    /// The method name of an Unknown message, e.g. `Foo.bar`
+    pub fn unknown_method(&self) -> Option<&str> {
+        match *self {
+            Event::Unknown(ref v) => v["method"].as_str(),
+            _ => None,
+        }
+    }
+}
+impl<'de> serde::Deserialize<'de> for Event {
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+        let mut v = JsonValue::deserialize(d)?;
+        // params can be omitted when empty, but the domain enums expect it
+        if let Some(obj) = v.as_object_mut() {
+            obj.entry("params").or_insert_with(|| JsonValue::Object(Default::default()));
+        }
+        let domain = match v.get("method").and_then(JsonValue::as_str) {
+            Some(method) => method.split('.').next().unwrap_or("").to_string(),
+            None => return Err(D::Error::missing_field("method")),
+        };
+        match domain.as_str() {
+            "Tree" => <Tree::Event as serde::Deserialize>::deserialize(v).map(Event::Tree).map_err(D::Error::custom),
+            _ => Ok(Event::Unknown(v)),
+        }
+    }
+}
@@ -0,0 +1,78 @@
+/// The protocol version these bindings were generated from
+pub const VERSION: (&str, &str) = ("1", "2");
+/// All protocol domains
+pub static DOMAINS: &[DomainInfo] = &[
+    DomainInfo {
+        name: "Tree",
+        description: Some("A small domain used to test the generator."),
+        experimental: false,
+        types: &[
+            TypeInfo { id: "NodeId", ty: "integer", items: None, enum_values: &[], properties: &[], description: Some("Unique node identifier.") },
+            TypeInfo { id: "Label", ty: "string", items: None, enum_values: &[], properties: &[], description: None },
+            TypeInfo { id: "Timestamp", ty: "number", items: None, enum_values: &[], properties: &[], description: None },
+            TypeInfo { id: "Point", ty: "array", items: Some("number"), enum_values: &[], properties: &[], description: None },
+            TypeInfo { id: "Mode", ty: "string", items: None, enum_values: &["fast-mode", "slow"], properties: &[], description: Some("Traversal mode.") },
+            TypeInfo { id: "Node", ty: "object", items: None, enum_values: &[], properties: &[ParamInfo { name: "nodeId", ty: "Tree.NodeId", items: None, optional: false, enum_values: &[], description: None }, ParamInfo { name: "parent", ty: "Tree.Node", items: None, optional: true, enum_values: &[], description: None }, ParamInfo { name: "children", ty: "array", items: Some("Tree.Node"), optional: true, enum_values: &[], description: None }, ParamInfo { name: "firstChild", ty: "Tree.Node", items: None, optional: false, enum_values: &[], description: None }], description: Some("A node that refers to itself.") },
+            TypeInfo { id: "Options", ty: "object", items: None, enum_values: &[], properties: &[ParamInfo { name: "depth", ty: "integer", items: None, optional: true, enum_values: &[], description: None }, ParamInfo { name: "mode", ty: "Tree.Mode", items: None, optional: true, enum_values: &[], description: None }], description: None },
+        ],
+        commands: &[
+            CommandInfo {
+                name: "enable",
+                description: Some("Enables events."),
+                experimental: false,
+                parameters: &[],
+                returns: &[],
+            },
+            CommandInfo {
+                name: "getNode",
+                description: None,
+                experimental: false,
+                parameters: &[ParamInfo { name: "nodeId", ty: "Tree.NodeId", items: None, optional: false, enum_values: &[], description: None },
+                    ParamInfo { name: "mode", ty: "Tree.Mode", items: None, optional: true, enum_values: &[], description: Some("How to \"walk\" the tree.") }],
+                returns: &[ParamInfo { name: "node", ty: "Tree.Node", items: None, optional: false, enum_values: &[], description: None },
+                    ParamInfo { name: "timestamp", ty: "Tree.Timestamp", items: None, optional: true, enum_values: &[], description: None }],
+            },
+            CommandInfo {
+                name: "setOptions",
+                description: None,
+                experimental: false,
+                parameters: &[ParamInfo { name: "options", ty: "Tree.Options", items: None, optional: true, enum_values: &[], description: None }],
+                returns: &[],
+            },
+        ],
+        events: &[
+            EventInfo {
+                name: "ready",
+                description: Some("Fired without parameters."),
+                experimental: false,
+                parameters: &[],
+            },
+            EventInfo {
+                name: "nodeChanged",
+                description: None,
+                experimental: false,
+                parameters: &[ParamInfo { name: "node", ty: "Tree.Node", items: None, optional: false, enum_values: &[], description: None },
+                    ParamInfo { name: "type", ty: "string", items: None, optional: false, enum_values: &[], description: None },
+                    ParamInfo { name: "label", ty: "Tree.Label", items: None, optional: true, enum_values: &[], description: None }],
+            },
+        ],
+    },
+    DomainInfo {
+        name: "Other",
+        description: None,
+        experimental: true,
+        types: &[
+        ],
+        commands: &[
+            CommandInfo {
+                name: "describe",
+                description: None,
+                experimental: false,
+                parameters: &[ParamInfo { name: "nodeIds", ty: "array", items: Some("Tree.NodeId"), optional: false, enum_values: &[], description: None }],
+                returns: &[ParamInfo { name: "labels", ty: "array", items: Some("Tree.Label"), optional: false, enum_values: &[], description: None }],
+            },
+        ],
+        events: &[
+        ],
+    },
+];
@@ -0,0 +1,225 @@
+
+use ::cxdbg::DebugClient;
+use ::cxdbg::Error as ClientError;
+use serde;
+use serde_json::Value as JsonValue;
+
+/// A dummy type for commands that return nothing
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Nothing {
+}
+
+fn deserialize_unit_enum<'de, D: serde::Deserializer<'de>>(_d: D) -> Result<(), D::Error> {
+    Ok(())
+}
+
+pub mod Foo {
+    #[allow(unused_imports)] use serde_json::Value as JsonValue;
+    #[allow(unused_imports)] use super::Nothing;
+    use super::*;
+    #[derive(Deserialize, Debug, Serialize, Clone, PartialEq)]
+    pub struct Widget {
+        #[serde(rename = "name")]
+        pub _name: String,
+        #[serde(rename = "object")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub _object: Option<super::Runtime::RemoteObjectId>,
+    }
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    pub struct ReturnType_bar {
+        #[serde(rename = "widget")]
+        pub widget: Widget,
+    }
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    pub struct Request_bar {
+        #[serde(rename = "nodeId")]
+        pub _nodeId: super::DOM::NodeId,
+    }
+    pub trait FooApi {
+        fn bar(&mut self, _nodeId: super::DOM::NodeId) -> Result<ReturnType_bar, ClientError>;
+    }
+    impl FooApi for DebugClient {
+        fn bar(&mut self, _nodeId: super::DOM::NodeId) -> Result<ReturnType_bar, ClientError> {
+            self.call("Foo.bar", Request_bar {_nodeId})
+        }
+    }
+    /// Commands accepted by the Foo domain
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    #[serde(tag = "method", content = "params")]
+    pub enum Command {
+        #[serde(rename = "Foo.bar")]
+        bar(Request_bar),
+    }
+    impl Command {
+        /// The protocol method name of this command
+        pub fn method_name(&self) -> &'static str {
+            match *self {
+                Command::bar(_) => "Foo.bar",
+            }
+        }
+        /// Parse the result of this command into the matching ReturnType variant
+        pub fn parse_result(&self, v: JsonValue) -> Result<ReturnType, ::serde_json::Error> {
+            match *self {
+                Command::bar(_) => ::serde_json::from_value(v).map(ReturnType::bar),
+            }
+        }
+    }
+    /// Results of the commands in the Foo domain
+    #[derive(Serialize, Debug, Clone, PartialEq)]
+    #[serde(untagged)]
+    pub enum ReturnType {
+        bar(ReturnType_bar),
+    }
+    /// Events emitted by the Foo domain
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    #[serde(tag = "method", content = "params")]
+    pub enum Event {
+        #[serde(rename = "Foo.widgetAdded")]
+        widgetAdded {
+            #[serde(rename = "widget")]
+            widget: Widget,
+        },
+    }
+    impl Event {
+        /// The protocol method name of this event
+        pub fn method_name(&self) -> &'static str {
+            match *self {
+                Event::widgetAdded { .. } => "Foo.widgetAdded",
+            }
+        }
+    }
+} // Foo
+pub use self::Foo::FooApi;
+/// A command accepted by the browser, grouped by protocol domain
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Command {
+    Foo(Foo::Command),
+    /// A message for a domain that is not in the protocol spec, e.g. from a protocol
+    /// extension. Holds the whole message.
+    Unknown(JsonValue),
+}
+impl Command {
+    /// The protocol domain, e.g. `Page`. Empty for Unknown, see unknown_method.
+    pub fn domain(&self) -> &'static str {
+        match *self {
+            Command::Foo(_) => "Foo",
+            Command::Unknown(_) => "",
+        }
+    }
+    /// The protocol method name, e.g. `Page.navigate`. Empty for Unknown, see
+    /// unknown_method.
+    pub fn method_name(&self) -> &'static str {
+        match *self {
+            Command::Foo(ref v) => v.method_name(),
+            Command::Unknown(_) => "",
+        }
+    }
+    /// The method name of an Unknown message, e.g. `Foo.bar`
+    pub fn unknown_method(&self) -> Option<&str> {
+        match *self {
+            Command::Unknown(ref v) => v["method"].as_str(),
+            _ => None,
+        }
+    }
+}
+impl<'de> serde::Deserialize<'de> for Command {
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+        let mut v = JsonValue::deserialize(d)?;
+        // params can be omitted when empty, but the domain enums expect it
+        if let Some(obj) = v.as_object_mut() {
+            obj.entry("params").or_insert_with(|| JsonValue::Object(Default::default()));
+        }
+        let domain = match v.get("method").and_then(JsonValue::as_str) {
+            Some(method) => method.split('.').next().unwrap_or("").to_string(),
+            None => return Err(D::Error::missing_field("method")),
+        };
+        match domain.as_str() {
+            "Foo" => <Foo::Command as serde::Deserialize>::deserialize(v).map(Command::Foo).map_err(D::Error::custom),
+            _ => Ok(Command::Unknown(v)),
+        }
+    }
+}
+/// The result of a Command, grouped by protocol domain
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum ReturnType {
+    Foo(Foo::ReturnType),
+    /// The result of an Unknown command
+    Unknown(JsonValue),
+}
+impl Command {
+    /// Parse the result of this command into the matching ReturnType variant
+    pub fn parse_result(&self, v: JsonValue) -> Result<ReturnType, ::serde_json::Error> {
+        match *self {
+            Command::Foo(ref cmd) => cmd.parse_result(v).map(ReturnType::Foo),
+            Command::Unknown(_) => Ok(ReturnType::Unknown(v)),
+        }
+    }
+}
+/// An event emitted by the browser, grouped by protocol domain
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Event {
+    Foo(Foo::Event),
+    /// A message for a domain that is not in the protocol spec, e.g. from a protocol
+    /// extension. Holds the whole message.
+    Unknown(JsonValue),
+}
+impl Event {
+    /// The protocol domain, e.g. `Page`. Empty for Unknown, see unknown_method.
+    pub fn domain(&self) -> &'static str {
+        match *self {
+            Event::Foo(_) => "Foo",
+            Event::Unknown(_) => "",
+        }
+    }
+    /// The protocol method name, e.g. `Page.navigate`. Empty for Unknown, see
+    /// unknown_method.
+    pub fn method_name(&self) -> &'static str {
+        match *self {
+            Event::Foo(ref v) => v.method_name(),
+            Event::Unknown(_) => "",
+        }
+    }
+    /// The method name of an Unknown message, e.g. `Foo.bar`
+    pub fn unknown_method(&self) -> Option<&str> {
+        match *self {
+            Event::Unknown(ref v) => v["method"].as_str(),
+            _ => None,
+        }
+    }
+}
+impl<'de> serde::Deserialize<'de> for Event {
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+        let mut v = JsonValue::deserialize(d)?;
+        // params can be omitted when empty, but the domain enums expect it
+        if let Some(obj) = v.as_object_mut() {
+            obj.entry("params").or_insert_with(|| JsonValue::Object(Default::default()));
+        }
+        let domain = match v.get("method").and_then(JsonValue::as_str) {
+            Some(method) => method.split('.').next().unwrap_or("").to_string(),
+            None => return Err(D::Error::missing_field("method")),
+        };
+        match domain.as_str() {
+            "Foo" => <Foo::Event as serde::Deserialize>::deserialize(v).map(Event::Foo).map_err(D::Error::custom),
+            _ => Ok(Event::Unknown(v)),
+        }
+    }
+}
+pub use ::cxdbg::proto::DOM;
+pub use ::cxdbg::proto::Runtime;
+impl Event {
+    /// Parse an event from these domains, that cxdbg reported as Unknown
+    pub fn from_unknown(ev: &::cxdbg::proto::Event) -> Option<Event> {
+        match *ev {
+            ::cxdbg::proto::Event::Unknown(ref v) => match ::serde_json::from_value(v.clone()) {
+                Ok(Event::Unknown(_)) | Err(_) => None,
+                Ok(ev) => Some(ev),
+            },
+            _ => None,
+        }
+    }
+}
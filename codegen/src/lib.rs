@@ -0,0 +1,871 @@
+//! Code generator for the chrome developer tools protocol
+//!
+//! Transforms the protocol spec (e.g. src/chrome_protocol.json in cxdbg) into rust code.
+
+extern crate serde_json;
+#[macro_use]
+extern crate serde_derive;
+
+use std::fs::File;
+use std::io::{Read, Write, Result, Error, ErrorKind};
+use std::path::Path;
+
+/// Generate bindings for protocol extension domains from a build script. `spec` is a JSON
+/// protocol spec, either a single domain or a list of domains, and the bindings are written
+/// to `dest`, usually in OUT_DIR. The crate that includes the bindings needs to depend on
+/// cxdbg, serde, serde_derive and serde_json.
+///
+/// ```ignore
+/// // build.rs
+/// let dest = Path::new(&env::var("OUT_DIR").unwrap()).join("foo.rs");
+/// cxdbg_codegen::generate_extension("protocol/foo.json", dest).unwrap();
+///
+/// // src/lib.rs
+/// #[allow(non_snake_case, non_camel_case_types)]
+/// pub mod foo {
+///     include!(concat!(env!("OUT_DIR"), "/foo.rs"));
+/// }
+/// ```
+pub fn generate_extension<P: AsRef<Path>, Q: AsRef<Path>>(spec: P, dest: Q) -> Result<()> {
+    println!("cargo:rerun-if-changed={}", spec.as_ref().display());
+    let p = ChromeDbgProto::from_reader(File::open(spec)?)
+        .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+    let mut f = File::create(dest)?;
+    p.genrust_extension(&mut f)
+}
+
+#[derive(Deserialize, Debug)]
+struct ChromeDbgEvent {
+    name: String,
+    description: Option<String>,
+    parameters: Option<Vec<ChromeDbgTypeDecl>>,
+    experimental: Option<bool>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ChromeDbgCommand {
+    name: String,
+    description: Option<String>,
+    parameters: Option<Vec<ChromeDbgTypeDecl>>,
+    returns: Option<Vec<ChromeDbgTypeDecl>>,
+    experimental: Option<bool>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ChromeDbgTypeDecl {
+    id: Option<String>,
+    #[serde(rename = "type")]
+    _type: Option<String>,
+    optional: Option<bool>,
+    #[serde(rename = "$ref")]
+    _ref: Option<String>,
+    items: Option<Box<ChromeDbgTypeDecl>>,
+    #[serde(rename = "enum")]
+    _enum: Option<Vec<String>>,
+    description: Option<String>,
+    name: Option<String>,
+    properties: Option<Vec<ChromeDbgTypeDecl>>,
+}
+
+impl ChromeDbgTypeDecl {
+    fn type_id(&self, absprefix: &str, relprefix: &str) -> Option<String> {
+        self.type_id_with_box(None, absprefix, relprefix)
+    }
+    fn type_id_with_box(&self, parent_type: Option<&str>, absprefix: &str, relprefix: &str) -> Option<String> {
+        let mut s = String::new();
+
+        if let Some(ref t) = self._type {
+            match t.as_str() {
+                "boolean" => s.push_str("bool"),
+                "string" => s.push_str("String"),
+                "integer" => s.push_str("i64"),
+                "number" => s.push_str("f64"),
+                "array" => {
+                    if let Some(t) = self.items.as_ref().and_then(|t| t.type_id(absprefix, relprefix)) {
+                        s = format!("Vec<{}>", t);
+                    } else {
+                        return None;
+                    }
+                }
+                "any" => s.push_str("JsonValue"),
+                "object" => s.push_str("JsonValue"),
+                _ => return None,
+            }
+        } else if let Some(ref r) = self._ref {
+            if r.contains(".") {
+                // r is an absolute reference
+                s = format!("{}{}", absprefix, &r.replace('.', "::"));
+            } else {
+                // r is a relative reference
+                s = format!("{}{}", relprefix, r);
+            }
+        } else {
+            return None;
+        }
+
+        if Some(s.as_str()) == parent_type {
+            s = format!("Box<{}>", s);
+        }
+
+        if self.optional.unwrap_or(false) {
+            Some(format!("Option<{}>", s))
+        } else {
+            Some(s)
+        }
+    }
+
+    /// The schema type of this declaration, references are qualified with their domain
+    fn schema_type(&self, domain: &str) -> String {
+        match (&self._type, &self._ref) {
+            (&Some(ref t), _) => t.clone(),
+            (&None, &Some(ref r)) if r.contains('.') => r.clone(),
+            (&None, &Some(ref r)) => format!("{}.{}", domain, r),
+            (&None, &None) => "any".to_string(),
+        }
+    }
+
+    /// A schema::TypeInfo literal for this declaration
+    fn genschema_type(&self, domain: &str) -> String {
+        let items = match self.items {
+            Some(ref items) => format!("Some({:?})", items.schema_type(domain)),
+            None => "None".to_string(),
+        };
+        let properties = self.properties.as_ref()
+            .map(|props| props.iter().map(|p| p.genschema_param(domain)).collect::<Vec<_>>().join(", "))
+            .unwrap_or_default();
+        format!("TypeInfo {{ id: {:?}, ty: {:?}, items: {}, enum_values: &{:?}, properties: &[{}], description: {} }}",
+                self.id.as_ref().expect("Domain type has no id"),
+                self.schema_type(domain),
+                items,
+                self._enum.as_ref().map(Vec::as_slice).unwrap_or(&[]),
+                properties,
+                description_literal(&self.description))
+    }
+
+    /// A schema::ParamInfo literal for this declaration
+    fn genschema_param(&self, domain: &str) -> String {
+        let items = match self.items {
+            Some(ref items) => format!("Some({:?})", items.schema_type(domain)),
+            None => "None".to_string(),
+        };
+        format!("ParamInfo {{ name: {:?}, ty: {:?}, items: {}, optional: {}, enum_values: &{:?}, description: {} }}",
+                self.name.as_ref().expect("Parameter has no name"),
+                self.schema_type(domain),
+                items,
+                self.optional.unwrap_or(false),
+                self._enum.as_ref().map(Vec::as_slice).unwrap_or(&[]),
+                description_literal(&self.description))
+    }
+
+    /// Collect the domains of all absolute references in this declaration
+    fn collect_refs(&self, out: &mut Vec<String>) {
+        if let Some(ref r) = self._ref {
+            if let Some(idx) = r.find('.') {
+                out.push(r[..idx].to_string());
+            }
+        }
+        if let Some(ref items) = self.items {
+            items.collect_refs(out);
+        }
+        for prop in self.properties.as_ref().map(Vec::as_slice).unwrap_or(&[]) {
+            prop.collect_refs(out);
+        }
+    }
+
+    /// The derives for a type declaration that should become a newtype, or None if this
+    /// is not an alias for a primitive type
+    fn newtype_derives(&self) -> Option<&'static str> {
+        if self._enum.is_some() || self.properties.is_some() {
+            return None;
+        }
+        match self._type.as_ref().map(String::as_str) {
+            Some("string") => Some("Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash"),
+            Some("integer") => Some("Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash"),
+            Some("number") => Some("Serialize, Deserialize, Debug, Clone, Copy, PartialEq, PartialOrd"),
+            _ => None,
+        }
+    }
+}
+
+/// A string literal for an optional description
+fn description_literal(d: &Option<String>) -> String {
+    match *d {
+        Some(ref d) => format!("Some({:?})", d.trim()),
+        None => "None".to_string(),
+    }
+}
+
+/// Derives for a generated struct, structs where all fields are optional also get Default
+fn struct_derives(base: &str, fields: &[ChromeDbgTypeDecl]) -> String {
+    if fields.iter().all(|f| f.optional.unwrap_or(false)) {
+        format!("{}, Clone, PartialEq, Default", base)
+    } else {
+        format!("{}, Clone, PartialEq", base)
+    }
+}
+
+#[derive(Deserialize)]
+struct ChromeDbgDomain {
+    domain: String,
+    description: Option<String>,
+    experimental: Option<bool>,
+    commands: Vec<ChromeDbgCommand>,
+    events: Option<Vec<ChromeDbgEvent>>,
+    types: Option<Vec<ChromeDbgTypeDecl>>,
+}
+
+impl ChromeDbgDomain {
+    fn genrust(&self, w: &mut Write) -> Result<()> {
+        writeln!(w, "pub mod {} {{", self.domain)?;
+
+        writeln!(w, "    #[allow(unused_imports)] use serde_json::Value as JsonValue;").unwrap();
+        writeln!(w, "    #[allow(unused_imports)] use super::Nothing;").unwrap();
+        writeln!(w, "    use super::*;").unwrap();
+
+        if let Some(ref types) = self.types {
+            for dtype in types {
+                if let Some(ref variants) = dtype._enum {
+                    // TODO Sadly serde does not support a fallback variant for enums
+                    // see https://github.com/serde-rs/serde/issues/912 for a workaround
+
+                    if let Some(ref d) = dtype.description {
+                        writeln!(w, "    /// {}", d.trim())?;
+                    }
+                    writeln!(w, "        #[derive(Deserialize, Debug, Serialize, Clone, PartialEq, Eq, Hash)]")?;
+                    writeln!(w, "        pub enum {} {{", dtype.id.as_ref().expect("Domain type has no id"))?;
+                    for var in variants {
+                        writeln!(w, r#"            #[serde(rename = "{}")]"#, var)?;
+                        writeln!(w, "            _{},", var.replace('-', "_"))?;
+                    }
+                    writeln!(w, "        }}" )?;
+                } else if let Some(ref properties) = dtype.properties {
+                    if let Some(ref d) = dtype.description {
+                        writeln!(w, "    /// {}", d.trim())?;
+                    }
+                    let dtype_id = dtype.id.as_ref().expect("Domain type has no id");
+                    writeln!(w, "    #[derive({})]", struct_derives("Deserialize, Debug, Serialize", properties))?;
+                    writeln!(w, "    pub struct {} {{", dtype_id)?;
+                    for prop in properties {
+                        let name = prop.name.as_ref().expect("Type property has no name");
+                        if let Some(t) = prop.type_id_with_box(Some(&dtype_id), "super::", "") {
+                        writeln!(w, r#"        #[serde(rename = "{}")]"#, name)?;
+                        if prop.optional.unwrap_or(false) {
+                            // Types are also used as parameters, where null is not the same as missing
+                            writeln!(w, r#"        #[serde(skip_serializing_if = "Option::is_none")]"#)?;
+                        }
+                        writeln!(w, "        pub _{}: {},", name, t)?;
+                        }
+                    }
+                    writeln!(w, "    }}" )?;
+                } else if let Some(derives) = dtype.newtype_derives() {
+                    // Primitive aliases are identifiers or timestamps, use distinct types so
+                    // they cannot be mixed up
+                    if let Some(ref d) = dtype.description {
+                        writeln!(w, "    /// {}", d.trim())?;
+                    }
+                    let dtype_id = dtype.id.as_ref().expect("Domain type has no id");
+                    let inner = dtype.type_id("super::", "").expect("Cannot determine newtype type");
+                    writeln!(w, "    #[derive({})]", derives)?;
+                    writeln!(w, "    #[serde(transparent)]")?;
+                    writeln!(w, "    pub struct {}(pub {});", dtype_id, inner)?;
+                    writeln!(w, r#"    impl ::std::fmt::Display for {} {{
+        fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {{
+            self.0.fmt(f)
+        }}
+    }}
+    impl From<{}> for {} {{
+        fn from(v: {}) -> Self {{
+            {}(v)
+        }}
+    }}"#, dtype_id, inner, dtype_id, inner, dtype_id)?;
+                } else if let Some(t) = dtype.type_id("super::", "") {
+                    writeln!(w, "    pub type {} = {};", dtype.id.as_ref().expect("Domain type has no id"), t)?;
+                } else {
+                    writeln!(w, "    pub type {} = JsonValue;", dtype.id.as_ref().expect("Domain type has no id"))?;
+                }
+            }
+        }
+
+
+
+        let mut cmd_type_info = Vec::new();
+        // commands
+        for cmd in &self.commands {
+            // Create a return type for this command, or use Nothing
+            let return_type_name = match cmd.returns.as_ref().map(Vec::as_slice).unwrap_or(&[]) {
+                [] => "Nothing".to_string(),
+                v => {
+                    writeln!(w, "    #[derive({})]", struct_derives("Serialize, Deserialize, Debug", v))?;
+                    writeln!(w, "    pub struct ReturnType_{} {{", cmd.name)?;
+                    for r in v {
+                        let name = r.name.as_ref().expect("Return type attr has no name");
+                        writeln!(w, r#"        #[serde(rename = "{}")]"#, name)?;
+                        if r.optional.unwrap_or(false) {
+                            writeln!(w, r#"        #[serde(skip_serializing_if = "Option::is_none")]"#)?;
+                        }
+                        writeln!(w, "        pub {}: {},",
+                                 name,
+                                 r.type_id("super::", "").as_ref().expect("Cannot determine return type"))?;
+                    }
+                    writeln!(w, "    }}")?;
+                    format!("ReturnType_{}", cmd.name)
+                }
+            };
+
+            // Create a request type for this command, this is used by the generated api
+            // functions and can also be used to parse incoming requests.
+            let request_type = if let Some(ref types) = cmd.parameters {
+                writeln!(w, r#"    #[derive({})]
+    pub struct Request_{} {{"#, struct_derives("Serialize, Deserialize, Debug", types), cmd.name)?;
+
+                for ty in types {
+                    if let Some(ref s) = ty.description {
+                            writeln!(w, "        /// {}", s)?;
+                    }
+
+                    let ty_name = ty.name.as_ref().expect("Argument has no name");
+                    writeln!(w, r#"        #[serde(rename = "{}")]"#, ty_name)?;
+                    if ty.optional.unwrap_or(false) {
+                        // Dont serialize optional arguments. By default serde uses null.
+                        writeln!(w, r#"        #[serde(skip_serializing_if = "Option::is_none")]"#)?;
+                    }
+                    writeln!(w, "        pub _{}: {},",
+                             ty_name,
+                             ty.type_id("super::", "").expect("Cannot determine type for argument"))?;
+                }
+                writeln!(w, "    }}")?;
+                format!("Request_{}", cmd.name)
+            } else {
+                "Nothing".to_string()
+            };
+
+            cmd_type_info.push((cmd, request_type, return_type_name));
+        }
+
+        // a domain trait for the sync api
+        writeln!(w, r#"    pub trait {}Api {{"#, &self.domain)?;
+        for (cmd, _, return_type_name) in &cmd_type_info {
+            if let Some(ref d) = cmd.description {
+                writeln!(w, "        /// {}", d.trim())?;
+            }
+            write!(w, r#"        fn {}(&mut self"#, cmd.name)?;
+            if let Some(ref types) = cmd.parameters {
+                for ty in types {
+                    write!(w, ", _{}: {}",
+                           ty.name.as_ref().expect("Argument has no name"),
+                           ty.type_id("super::", "").expect("Cannot determine type for argument"))?;
+                }
+            }
+            writeln!(w, r#") -> Result<{}, ClientError>;"#, return_type_name)?;
+        }
+        writeln!(w, r#"    }}"#)?;
+
+        writeln!(w, r#"    impl {}Api for DebugClient {{"#, &self.domain)?;
+        for (cmd, request_type, return_type_name) in &cmd_type_info {
+            write!(w, r#"        fn {}(&mut self"#, cmd.name)?;
+            if let Some(ref types) = cmd.parameters {
+                for ty in types {
+                    write!(w, ", _{}: {}",
+                           ty.name.as_ref().expect("Argument has no name"),
+                           ty.type_id("super::", "").expect("Cannot determine type for argument"))?;
+                }
+            }
+
+            let fullname = format!("{}.{}", &self.domain, cmd.name);
+            writeln!(w, r#") -> Result<{}, ClientError> {{"#, return_type_name)?;
+            write!(w, r#"            self.call("{}", {} {{"#, fullname, request_type)?;
+            if let Some(ref types) = cmd.parameters {
+                for (idx, ty) in types.iter().enumerate() {
+                    if idx != 0 {
+                        write!(w, ",")?;
+                    }
+                    write!(w, "_{}", ty.name.as_ref().expect("Argument type is missing a name"))?;
+                }
+            }
+            writeln!(w, r#"}})"#)?;
+
+            writeln!(w, r#"        }}"#)?;
+        }
+        writeln!(w, r#"    }}"#)?;
+
+        self.genrust_commands(w, &cmd_type_info)?;
+        self.genrust_events(w)?;
+
+        writeln!(w, "}} // {}", self.domain)?;
+        Ok(())
+    }
+
+    /// Generate the Command enum for this domain, each variant holds the request type. The
+    /// ReturnType enum mirrors it with the return type of each command.
+    fn genrust_commands(&self, w: &mut Write, cmd_type_info: &[(&ChromeDbgCommand, String, String)]) -> Result<()> {
+        writeln!(w, "    /// Commands accepted by the {} domain", &self.domain)?;
+        writeln!(w, "    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]")?;
+        writeln!(w, r#"    #[serde(tag = "method", content = "params")]"#)?;
+        writeln!(w, "    pub enum Command {{")?;
+        for (cmd, request_type, _) in cmd_type_info {
+            if let Some(ref d) = cmd.description {
+                writeln!(w, "        /// {}", d.trim())?;
+            }
+            writeln!(w, r#"        #[serde(rename = "{}.{}")]"#, &self.domain, cmd.name)?;
+            writeln!(w, "        {}({}),", cmd.name, request_type)?;
+        }
+        writeln!(w, "    }}")?;
+
+        writeln!(w, "    impl Command {{")?;
+        writeln!(w, "        /// The protocol method name of this command")?;
+        writeln!(w, "        pub fn method_name(&self) -> &'static str {{")?;
+        writeln!(w, "            match *self {{")?;
+        for (cmd, _, _) in cmd_type_info {
+            writeln!(w, r#"                Command::{}(_) => "{}.{}","#, cmd.name, &self.domain, cmd.name)?;
+        }
+        writeln!(w, "            }}")?;
+        writeln!(w, "        }}")?;
+        writeln!(w, "        /// Parse the result of this command into the matching ReturnType variant")?;
+        writeln!(w, "        pub fn parse_result(&self, v: JsonValue) -> Result<ReturnType, ::serde_json::Error> {{")?;
+        writeln!(w, "            match *self {{")?;
+        for (cmd, _, _) in cmd_type_info {
+            writeln!(w, "                Command::{}(_) => ::serde_json::from_value(v).map(ReturnType::{}),", cmd.name, cmd.name)?;
+        }
+        writeln!(w, "            }}")?;
+        writeln!(w, "        }}")?;
+        writeln!(w, "    }}")?;
+
+        writeln!(w, "    /// Results of the commands in the {} domain", &self.domain)?;
+        writeln!(w, "    #[derive(Serialize, Debug, Clone, PartialEq)]")?;
+        writeln!(w, "    #[serde(untagged)]")?;
+        writeln!(w, "    pub enum ReturnType {{")?;
+        for (cmd, _, return_type_name) in cmd_type_info {
+            writeln!(w, "        {}({}),", cmd.name, return_type_name)?;
+        }
+        writeln!(w, "    }}")?;
+        Ok(())
+    }
+
+    /// Generate the Event enum for this domain, domains without events get no enum
+    fn genrust_events(&self, w: &mut Write) -> Result<()> {
+        let events = match self.events {
+            Some(ref events) if !events.is_empty() => events,
+            _ => return Ok(()),
+        };
+
+        writeln!(w, "    /// Events emitted by the {} domain", &self.domain)?;
+        writeln!(w, "    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]")?;
+        writeln!(w, r#"    #[serde(tag = "method", content = "params")]"#)?;
+        writeln!(w, "    pub enum Event {{")?;
+        for ev in events {
+            let fullname = format!("{}.{}", &self.domain, ev.name);
+
+            if let Some(ref s) = ev.description {
+                writeln!(w, "        /// {}", s.trim())?;
+            }
+            writeln!(w, r#"        #[serde(rename = "{}")]"#, fullname)?;
+            if let Some(ref types) = ev.parameters {
+                writeln!(w, r#"        {} {{"#, ev.name)?;
+                for ty in types {
+                    let name = ty.name.as_ref().expect("Argument has no name");
+                    let fixed_name = match name.as_str() {
+                        "type" => "_type",
+                        _ => name,
+                    };
+                    if ty.optional.unwrap_or(false) {
+                        writeln!(w, r#"            #[serde(skip_serializing_if = "Option::is_none")]"#)?;
+                    }
+                    writeln!(w, r#"            #[serde(rename = "{}")]
+            {}: {},"#,
+                             name,
+                             fixed_name,
+                             ty.type_id("super::", "").expect("Argument has no type"))?;
+                }
+                writeln!(w, r#"        }},"#)?;
+            } else {
+                // when serde decodes these unit variants it does not accept a map, but
+                // that is what chrome hands us
+                writeln!(w, r#"        #[serde(deserialize_with="super::deserialize_unit_enum")]"#)?;
+                writeln!(w, r#"        {},"#, ev.name)?;
+            }
+        }
+        writeln!(w, "    }}")?;
+
+        writeln!(w, "    impl Event {{")?;
+        writeln!(w, "        /// The protocol method name of this event")?;
+        writeln!(w, "        pub fn method_name(&self) -> &'static str {{")?;
+        writeln!(w, "            match *self {{")?;
+        for ev in events {
+            let pattern = if ev.parameters.is_some() { " { .. }" } else { "" };
+            writeln!(w, r#"                Event::{}{} => "{}.{}","#, ev.name, pattern, &self.domain, ev.name)?;
+        }
+        writeln!(w, "            }}")?;
+        writeln!(w, "        }}")?;
+        writeln!(w, "    }}")?;
+        Ok(())
+    }
+
+    /// Generate the schema::DomainInfo for this domain
+    fn genschema(&self, w: &mut Write) -> Result<()> {
+        let params = |decls: &Option<Vec<ChromeDbgTypeDecl>>| {
+            decls.as_ref()
+                .map(|decls| decls.iter().map(|p| p.genschema_param(&self.domain)).collect::<Vec<_>>().join(",\n                    "))
+                .unwrap_or_default()
+        };
+
+        writeln!(w, "    DomainInfo {{")?;
+        writeln!(w, "        name: {:?},", &self.domain)?;
+        writeln!(w, "        description: {},", description_literal(&self.description))?;
+        writeln!(w, "        experimental: {},", self.experimental.unwrap_or(false))?;
+        writeln!(w, "        types: &[")?;
+        for dtype in self.types.as_ref().map(Vec::as_slice).unwrap_or(&[]) {
+            writeln!(w, "            {},", dtype.genschema_type(&self.domain))?;
+        }
+        writeln!(w, "        ],")?;
+        writeln!(w, "        commands: &[")?;
+        for cmd in &self.commands {
+            writeln!(w, "            CommandInfo {{")?;
+            writeln!(w, "                name: {:?},", cmd.name)?;
+            writeln!(w, "                description: {},", description_literal(&cmd.description))?;
+            writeln!(w, "                experimental: {},", cmd.experimental.unwrap_or(false))?;
+            writeln!(w, "                parameters: &[{}],", params(&cmd.parameters))?;
+            writeln!(w, "                returns: &[{}],", params(&cmd.returns))?;
+            writeln!(w, "            }},")?;
+        }
+        writeln!(w, "        ],")?;
+        writeln!(w, "        events: &[")?;
+        for ev in self.events.as_ref().map(Vec::as_slice).unwrap_or(&[]) {
+            writeln!(w, "            EventInfo {{")?;
+            writeln!(w, "                name: {:?},", ev.name)?;
+            writeln!(w, "                description: {},", description_literal(&ev.description))?;
+            writeln!(w, "                experimental: {},", ev.experimental.unwrap_or(false))?;
+            writeln!(w, "                parameters: &[{}],", params(&ev.parameters))?;
+            writeln!(w, "            }},")?;
+        }
+        writeln!(w, "        ],")?;
+        writeln!(w, "    }},")?;
+        Ok(())
+    }
+
+    /// Collect the domains of all absolute references in this domain
+    fn collect_refs(&self, out: &mut Vec<String>) {
+        let decls = self.types.iter()
+            .chain(self.commands.iter().flat_map(|c| c.parameters.iter().chain(c.returns.iter())))
+            .chain(self.events.iter().flat_map(|evs| evs.iter()).flat_map(|e| e.parameters.iter()));
+        for decl in decls.flat_map(|d| d.iter()) {
+            decl.collect_refs(out);
+        }
+    }
+
+    fn has_events(&self) -> bool {
+        self.events.as_ref().map(|ev| !ev.is_empty()).unwrap_or(false)
+    }
+}
+
+/// A parsed protocol spec
+#[derive(Deserialize)]
+pub struct ChromeDbgProto {
+    version: Option<ChromeDbgVersion>,
+    domains: Vec<ChromeDbgDomain>,
+}
+
+#[derive(Deserialize)]
+struct ChromeDbgVersion {
+    major: String,
+    minor: String,
+}
+
+impl ChromeDbgProto {
+    /// Parse a protocol spec in JSON format, this is either a full protocol with a list
+    /// of domains, or a single domain
+    pub fn from_reader<R: Read>(r: R) -> serde_json::Result<Self> {
+        let v: serde_json::Value = serde_json::from_reader(r)?;
+        if v.get("domains").is_some() {
+            serde_json::from_value(v)
+        } else {
+            Ok(ChromeDbgProto {
+                version: None,
+                domains: vec![serde_json::from_value(v)?],
+            })
+        }
+    }
+
+    /// Generate the static protocol metadata used by the schema module
+    pub fn genschema(&self, f: &mut Write) -> Result<()> {
+        let version = match self.version {
+            Some(ref version) => version,
+            None => return Err(Error::new(ErrorKind::InvalidData, "Protocol spec has no version")),
+        };
+        writeln!(f, "/// The protocol version these bindings were generated from")?;
+        writeln!(f, "pub const VERSION: (&str, &str) = ({:?}, {:?});",
+                 version.major, version.minor)?;
+        writeln!(f, "/// All protocol domains")?;
+        writeln!(f, "pub static DOMAINS: &[DomainInfo] = &[")?;
+        for domain in &self.domains {
+            domain.genschema(f)?;
+        }
+        writeln!(f, "];")
+    }
+
+    /// Generate a top level enum named `name`, each variant holds the enum with the same
+    /// name from a domain module. Only domains for which `filter` is true are included.
+    fn genrust_domain_enum(&self, f: &mut Write, name: &str, doc: &str, filter: fn(&ChromeDbgDomain) -> bool) -> Result<()> {
+        let domains: Vec<&ChromeDbgDomain> = self.domains.iter()
+            .filter(|d| filter(d))
+            .collect();
+
+        writeln!(f, "/// {}", doc)?;
+        writeln!(f, "#[derive(Serialize, Debug, Clone, PartialEq)]")?;
+        writeln!(f, "#[serde(untagged)]")?;
+        writeln!(f, "pub enum {} {{", name)?;
+        for domain in &domains {
+            writeln!(f, "    {}({}::{}),", &domain.domain, &domain.domain, name)?;
+        }
+        writeln!(f, "    /// A message for a domain that is not in the protocol spec, e.g. from a protocol")?;
+        writeln!(f, "    /// extension. Holds the whole message.")?;
+        writeln!(f, "    Unknown(JsonValue),")?;
+        writeln!(f, "}}")?;
+
+        writeln!(f, "impl {} {{", name)?;
+        writeln!(f, "    /// The protocol domain, e.g. `Page`. Empty for Unknown, see unknown_method.")?;
+        writeln!(f, "    pub fn domain(&self) -> &'static str {{")?;
+        writeln!(f, "        match *self {{")?;
+        for domain in &domains {
+            writeln!(f, r#"            {}::{}(_) => "{}","#, name, &domain.domain, &domain.domain)?;
+        }
+        writeln!(f, r#"            {}::Unknown(_) => "","#, name)?;
+        writeln!(f, "        }}")?;
+        writeln!(f, "    }}")?;
+        writeln!(f, "    /// The protocol method name, e.g. `Page.navigate`. Empty for Unknown, see")?;
+        writeln!(f, "    /// unknown_method.")?;
+        writeln!(f, "    pub fn method_name(&self) -> &'static str {{")?;
+        writeln!(f, "        match *self {{")?;
+        for domain in &domains {
+            writeln!(f, "            {}::{}(ref v) => v.method_name(),", name, &domain.domain)?;
+        }
+        writeln!(f, r#"            {}::Unknown(_) => "","#, name)?;
+        writeln!(f, "        }}")?;
+        writeln!(f, "    }}")?;
+        writeln!(f, "    /// The method name of an Unknown message, e.g. `Foo.bar`")?;
+        writeln!(f, "    pub fn unknown_method(&self) -> Option<&str> {{")?;
+        writeln!(f, "        match *self {{")?;
+        writeln!(f, r#"            {}::Unknown(ref v) => v["method"].as_str(),"#, name)?;
+        writeln!(f, "            _ => None,")?;
+        writeln!(f, "        }}")?;
+        writeln!(f, "    }}")?;
+        writeln!(f, "}}")?;
+
+        // Deserialization picks the domain enum from the method prefix, this keeps
+        // the error messages from the domain enum instead of a generic untagged error
+        writeln!(f, r#"impl<'de> serde::Deserialize<'de> for {} {{
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {{
+        use serde::de::Error;
+        let mut v = JsonValue::deserialize(d)?;
+        // params can be omitted when empty, but the domain enums expect it
+        if let Some(obj) = v.as_object_mut() {{
+            obj.entry("params").or_insert_with(|| JsonValue::Object(Default::default()));
+        }}
+        let domain = match v.get("method").and_then(JsonValue::as_str) {{
+            Some(method) => method.split('.').next().unwrap_or("").to_string(),
+            None => return Err(D::Error::missing_field("method")),
+        }};
+        match domain.as_str() {{"#, name)?;
+        for domain in &domains {
+            writeln!(f, r#"            "{}" => <{}::{} as serde::Deserialize>::deserialize(v).map({}::{}).map_err(D::Error::custom),"#,
+                     &domain.domain, &domain.domain, name, name, &domain.domain)?;
+        }
+        writeln!(f, r#"            _ => Ok({}::Unknown(v)),
+        }}
+    }}
+}}"#, name)
+    }
+
+    /// Generate the top level ReturnType enum, and the Command method to parse results into it
+    fn genrust_return_types(&self, f: &mut Write) -> Result<()> {
+        writeln!(f, "/// The result of a Command, grouped by protocol domain")?;
+        writeln!(f, "#[derive(Serialize, Debug, Clone, PartialEq)]")?;
+        writeln!(f, "#[serde(untagged)]")?;
+        writeln!(f, "pub enum ReturnType {{")?;
+        for domain in &self.domains {
+            writeln!(f, "    {}({}::ReturnType),", &domain.domain, &domain.domain)?;
+        }
+        writeln!(f, "    /// The result of an Unknown command")?;
+        writeln!(f, "    Unknown(JsonValue),")?;
+        writeln!(f, "}}")?;
+
+        writeln!(f, "impl Command {{")?;
+        writeln!(f, "    /// Parse the result of this command into the matching ReturnType variant")?;
+        writeln!(f, "    pub fn parse_result(&self, v: JsonValue) -> Result<ReturnType, ::serde_json::Error> {{")?;
+        writeln!(f, "        match *self {{")?;
+        for domain in &self.domains {
+            writeln!(f, "            Command::{}(ref cmd) => cmd.parse_result(v).map(ReturnType::{}),", &domain.domain, &domain.domain)?;
+        }
+        writeln!(f, "            Command::Unknown(_) => Ok(ReturnType::Unknown(v)),")?;
+        writeln!(f, "        }}")?;
+        writeln!(f, "    }}")?;
+        writeln!(f, "}}")
+    }
+
+    /// Generate the protocol types and the sync api for DebugClient
+    pub fn genrust(&self, f: &mut Write) -> Result<()> {
+        self.genrust_in_crate(f, "")
+    }
+
+    /// Generate bindings for protocol extension domains, to be included in a crate that
+    /// depends on cxdbg. References to domains that are not part of the extension resolve
+    /// to the domains in `cxdbg::proto`.
+    pub fn genrust_extension(&self, f: &mut Write) -> Result<()> {
+        self.genrust_in_crate(f, "::cxdbg::")?;
+
+        let mut foreign = Vec::new();
+        for domain in &self.domains {
+            domain.collect_refs(&mut foreign);
+        }
+        foreign.retain(|d| !self.domains.iter().any(|local| &local.domain == d));
+        foreign.sort();
+        foreign.dedup();
+        for domain in foreign {
+            writeln!(f, "pub use ::cxdbg::proto::{};", domain)?;
+        }
+
+        writeln!(f, r#"impl Event {{
+    /// Parse an event from these domains, that cxdbg reported as Unknown
+    pub fn from_unknown(ev: &::cxdbg::proto::Event) -> Option<Event> {{
+        match *ev {{
+            ::cxdbg::proto::Event::Unknown(ref v) => match ::serde_json::from_value(v.clone()) {{
+                Ok(Event::Unknown(_)) | Err(_) => None,
+                Ok(ev) => Some(ev),
+            }},
+            _ => None,
+        }}
+    }}
+}}"#)
+    }
+
+    /// Generate the protocol code, `krate` is the path prefix for the cxdbg crate
+    fn genrust_in_crate(&self, f: &mut Write, krate: &str) -> Result<()> {
+        writeln!(f, r#"
+use {krate}DebugClient;
+use {krate}Error as ClientError;
+use serde;
+use serde_json::Value as JsonValue;
+
+/// A dummy type for commands that return nothing
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Nothing {{
+}}
+
+fn deserialize_unit_enum<'de, D: serde::Deserializer<'de>>(_d: D) -> Result<(), D::Error> {{
+    Ok(())
+}}
+"#, krate = krate)?;
+
+        for domain in &self.domains {
+            domain.genrust(f)?;
+            writeln!(f, "pub use self::{}::{}Api;", &domain.domain, &domain.domain)?;
+        }
+
+        self.genrust_domain_enum(f, "Command", "A command accepted by the browser, grouped by protocol domain", |_| true)?;
+        self.genrust_return_types(f)?;
+        self.genrust_domain_enum(f, "Event", "An event emitted by the browser, grouped by protocol domain", ChromeDbgDomain::has_events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+
+    const FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/basic.json");
+
+    fn fixture() -> ChromeDbgProto {
+        ChromeDbgProto::from_reader(fs::File::open(FIXTURE).unwrap()).unwrap()
+    }
+
+    fn decl(json: &str) -> ChromeDbgTypeDecl {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn genrust(p: &ChromeDbgProto) -> String {
+        let mut out = Vec::new();
+        p.genrust(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn genschema(p: &ChromeDbgProto) -> String {
+        let mut out = Vec::new();
+        p.genschema(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    /// Compare output with a golden file, set CXDBG_UPDATE_SNAPSHOTS to rewrite them
+    fn assert_snapshot(name: &str, output: &str) {
+        let path = format!("{}/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name);
+        if env::var_os("CXDBG_UPDATE_SNAPSHOTS").is_some() {
+            fs::write(&path, output).unwrap();
+        }
+        let expected = fs::read_to_string(&path).unwrap();
+        assert!(expected == output, "Generated code does not match {}, set CXDBG_UPDATE_SNAPSHOTS=1 to update it", path);
+    }
+
+    #[test]
+    fn type_ids() {
+        assert_eq!(decl(r#"{"type": "integer"}"#).type_id("super::", ""), Some("i64".to_string()));
+        assert_eq!(decl(r#"{"type": "string", "optional": true}"#).type_id("super::", ""), Some("Option<String>".to_string()));
+        assert_eq!(decl(r#"{"type": "array", "items": {"type": "number"}}"#).type_id("super::", ""), Some("Vec<f64>".to_string()));
+        assert_eq!(decl(r#"{"type": "array"}"#).type_id("super::", ""), None);
+        assert_eq!(decl(r#"{"type": "object"}"#).type_id("super::", ""), Some("JsonValue".to_string()));
+        assert_eq!(decl(r#"{"$ref": "NodeId"}"#).type_id("super::", "DOM::"), Some("DOM::NodeId".to_string()));
+        assert_eq!(decl(r#"{"$ref": "DOM.NodeId"}"#).type_id("super::", ""), Some("super::DOM::NodeId".to_string()));
+        assert_eq!(decl(r#"{}"#).type_id("super::", ""), None);
+    }
+
+    #[test]
+    fn recursive_types_are_boxed() {
+        let node = decl(r#"{"$ref": "Node"}"#);
+        assert_eq!(node.type_id_with_box(Some("Node"), "super::", ""), Some("Box<Node>".to_string()));
+        assert_eq!(node.type_id_with_box(Some("Other"), "super::", ""), Some("Node".to_string()));
+
+        let parent = decl(r#"{"$ref": "Node", "optional": true}"#);
+        assert_eq!(parent.type_id_with_box(Some("Node"), "super::", ""), Some("Option<Box<Node>>".to_string()));
+
+        // Vec already provides indirection
+        let children = decl(r#"{"type": "array", "items": {"$ref": "Node"}}"#);
+        assert_eq!(children.type_id_with_box(Some("Node"), "super::", ""), Some("Vec<Node>".to_string()));
+    }
+
+    #[test]
+    fn enum_variants_with_dashes() {
+        let out = genrust(&fixture());
+        assert!(out.contains("#[serde(rename = \"fast-mode\")]\n            _fast_mode,"));
+    }
+
+    #[test]
+    fn events_without_parameters() {
+        let out = genrust(&fixture());
+        assert!(out.contains("#[serde(deserialize_with=\"super::deserialize_unit_enum\")]\n        ready,"));
+        assert!(out.contains("Event::ready => \"Tree.ready\","));
+        // Other has no events, so it is not part of the top level Event enum
+        assert!(!out.contains("Other(Other::Event)"));
+    }
+
+    #[test]
+    fn snapshots() {
+        let p = fixture();
+        assert_snapshot("basic_proto.rs", &genrust(&p));
+        assert_snapshot("basic_schema.rs", &genschema(&p));
+    }
+
+    #[test]
+    fn extension_snapshot() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/extension.json");
+        let p = ChromeDbgProto::from_reader(fs::File::open(path).unwrap()).unwrap();
+        assert_eq!(p.domains.len(), 1);
+
+        let mut out = Vec::new();
+        p.genrust_extension(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("use ::cxdbg::DebugClient;"));
+        assert!(out.contains("pub use ::cxdbg::proto::DOM;\npub use ::cxdbg::proto::Runtime;\n"));
+        assert_snapshot("extension_proto.rs", &out);
+
+        // Extensions have no version, so there is no schema
+        assert!(p.genschema(&mut Vec::new()).is_err());
+    }
+}
@@ -1,8 +1,8 @@
 
 extern crate cxdbg;
 use cxdbg::DebugClient;
-use cxdbg::proto::{Event, PageApi, NetworkApi};
-use cxdbg::proto::Event::*;
+use cxdbg::schema;
+use cxdbg::proto::{Event, PageApi, NetworkApi, Inspector, Network};
 
 extern crate rustyline;
 use rustyline::error::ReadlineError;
@@ -13,74 +13,41 @@ use std::sync::{Arc, Mutex};
 
 fn process_event(ev: &Event) {
     match ev {
-        Inspector_detached { reason } => {
-            println!("Inspector detached: {}", reason);
-        }
-        Inspector_targetCrashed => {
-            println!("Inspector target has crashed");
-        }
-        Performance_metrics { .. } => (),
-        Page_domContentEventFired { .. } => {
-        }
-        Page_loadEventFired { .. } => (),
-        Page_lifecycleEvent { .. } => (),
-        Page_frameAttached { frameId, parentFrameId, stack } => (),
-        Page_frameNavigated { .. } => (),
-        Page_frameDetached { frameId } => (),
-        Page_frameStartedLoading { frameId } => (),
-        Page_frameStoppedLoading { frameId } => (),
-        Page_frameScheduledNavigation { .. } => (),
-        Page_frameClearedScheduledNavigation { .. } => (),
-        Page_frameResized => (),
-        Page_javascriptDialogOpening { .. } => (),
-        Page_javascriptDialogClosed { .. } => (),
-        Page_screencastFrame { .. } => (),
-        Page_screencastVisibilityChanged { visible } => (),
-        Page_interstitialShown => (),
-        Network_resourceChangedPriority { .. } => (),
-        Network_requestWillBeSent { requestId, loaderId, documentURL, .. } => {
+        Event::Inspector(ev) => match ev {
+            Inspector::Event::detached { reason } => {
+                println!("Inspector detached: {}", reason);
+            }
+            Inspector::Event::targetCrashed => {
+                println!("Inspector target has crashed");
+            }
+        },
+        Event::Network(Network::Event::requestWillBeSent { documentURL, .. }) => {
             println!("Request {}", documentURL);
         }
-        Network_requestServedFromCache { .. } => (),
-        Network_responseReceived { .. } => {
-        }
-        Network_dataReceived { .. } => (),
-        Network_loadingFinished { .. } => {
-        }
-        Network_loadingFailed { .. } => {
-        }
-        Network_webSocketWillSendHandshakeRequest {..} => {
-        }
-        Network_webSocketHandshakeResponseReceived {..} => {
-        }
-        Network_webSocketCreated { .. } => {
-        }
-        Network_webSocketClosed { .. } => {
-        }
-        Network_webSocketFrameReceived { .. } => {
-        }
-        Network_webSocketFrameError { .. } => {
-        }
-        Network_webSocketFrameSent { .. } => {
-        }
-        Network_eventSourceMessageReceived { .. } => (),
-        Network_requestIntercepted { .. } => (),
-        Target_targetCreated { targetInfo } => {
-        }
-        Target_targetInfoChanged { targetInfo } => {
-        }
-        Target_targetDestroyed { targetId } => {
-        }
-        Target_attachedToTarget { sessionId, targetInfo, waitingForDebugger } => {
-        }
-        Target_detachedFromTarget { sessionId, targetId } => {
-        }
-        Target_receivedMessageFromTarget { .. } => {
-        }
         _ => (),
     }
 }
 
+/// Print the domains, the methods in a domain, or the documentation for a method
+fn help(topic: &str) {
+    if topic.is_empty() {
+        for d in schema::DOMAINS {
+            println!("{}", d.name);
+        }
+    } else if let Some(cmd) = schema::command(topic) {
+        println!("{}\n    {}", topic, cmd.description.unwrap_or(""));
+        for p in cmd.parameters {
+            println!("    {}{}: {}", p.name, if p.optional { "?" } else { "" }, p.ty);
+        }
+    } else if let Some(d) = schema::domain(topic) {
+        for cmd in d.commands {
+            println!("{}.{}", d.name, cmd.name);
+        }
+    } else {
+        println!("Unknown domain or method: {}", topic);
+    }
+}
+
 fn main() {
     let mut c = DebugClient::connect(9222);
     PageApi::enable(&mut c).expect("Could not enable Page events");
@@ -105,7 +72,11 @@ fn main() {
         match readline {
             Ok(line) => {
                 rl.add_history_entry(line.as_ref());
-                println!("Line: {}", line);
+                if line.starts_with("help") {
+                    help(line["help".len()..].trim());
+                } else {
+                    println!("Line: {}", line);
+                }
             },
             Err(ReadlineError::Interrupted) => {
                 println!("CTRL-C");
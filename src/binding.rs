@@ -0,0 +1,159 @@
+//! Functions exposed to page JavaScript, backed by Rust closures
+
+use serde_json::{self, Value};
+
+use proto::{Event, Page, PageApi, Runtime, RuntimeApi};
+use runtime::check_exception;
+use {DebugClient, Error};
+
+/// Prefix of the console messages sent by exposed functions
+const BINDING_PREFIX: &str = "cxdbg:binding:";
+
+/// A function added by expose_function
+pub(crate) struct Binding {
+    /// Gets the call arguments as an array
+    callback: Box<dyn FnMut(Value) -> Value + Send>,
+    /// The script that adds the function to new documents
+    script: Page::ScriptIdentifier,
+}
+
+/// The Runtime.evaluate params that resolve a call
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct ResolveCall {
+    expression: String,
+    silent: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    context_id: Option<Runtime::ExecutionContextId>,
+}
+
+/// A call of an exposed function, decoded from a console message
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+struct BindingCall {
+    name: String,
+    seq: u64,
+    args: Value,
+    #[serde(skip)]
+    context: Option<Runtime::ExecutionContextId>,
+}
+
+impl BindingCall {
+    fn from_event(ev: &Event) -> Option<BindingCall> {
+        match *ev {
+            Event::Runtime(Runtime::Event::consoleAPICalled { ref _type, ref args, executionContextId, .. }) if _type == "debug" => {
+                let payload = match args.first().and_then(|a| a._value.as_ref()) {
+                    Some(Value::String(s)) if s.starts_with(BINDING_PREFIX) => &s[BINDING_PREFIX.len()..],
+                    _ => return None,
+                };
+                let mut call: BindingCall = serde_json::from_str(payload).ok()?;
+                call.context = Some(executionContextId);
+                Some(call)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The page side of a binding, calls are queued as console messages and return a promise
+fn binding_script(name: &str) -> Result<String, Error> {
+    Ok(format!(r#"(function() {{
+    var name = {name}, prefix = {prefix};
+    var debug = console.debug.bind(console);
+    var calls = window.__cxdbgCalls || (window.__cxdbgCalls = {{seq: 0, pending: {{}}}});
+    window[name] = function() {{
+        var args = Array.prototype.slice.call(arguments);
+        var seq = ++calls.seq;
+        return new Promise(function(resolve) {{
+            calls.pending[seq] = resolve;
+            debug(prefix + JSON.stringify({{name: name, seq: seq, args: args}}));
+        }});
+    }};
+}})()"#, name = serde_json::to_string(name)?, prefix = serde_json::to_string(BINDING_PREFIX)?))
+}
+
+/// Resolve the promise of a call with the result
+fn resolve_script(seq: u64, result: &Value) -> Result<String, Error> {
+    Ok(format!("(function(calls) {{ var resolve = calls.pending[{seq}]; delete calls.pending[{seq}]; resolve({result}); }})(window.__cxdbgCalls)",
+        seq = seq, result = serde_json::to_string(result)?))
+}
+
+impl DebugClient {
+    /// Make `window.<name>(...)` call a closure. The page gets a promise for the result,
+    /// which is resolved while the client handles events, e.g. during other commands or
+    /// poll. The function is added to the current document and all new documents, a
+    /// function exposed before with the same name is replaced.
+    pub fn expose_function<F>(&mut self, name: &str, f: F) -> Result<(), Error>
+        where F: FnMut(Value) -> Value + Send + 'static
+    {
+        let script = binding_script(name)?;
+        self.enable_contexts()?;
+        PageApi::enable(self)?;
+        // Register first, the old function is kept if this fails
+        let identifier = PageApi::addScriptToEvaluateOnNewDocument(self, script.clone())?.identifier;
+        let binding = Binding { callback: Box::new(f), script: identifier };
+        if let Some(old) = self.bindings.insert(name.to_string(), binding) {
+            PageApi::removeScriptToEvaluateOnNewDocument(self, old.script)?;
+        }
+        let r = RuntimeApi::evaluate(self, script, None, None, Some(true), None, None, None, None, None)?;
+        check_exception(r.exceptionDetails)
+    }
+
+    /// Run the closure for a call of an exposed function, returns whether the event was a
+    /// call. The promise is resolved after the event is handled.
+    pub(crate) fn handle_binding_call(&mut self, ev: &Event) -> Result<bool, Error> {
+        let call = match BindingCall::from_event(ev) {
+            Some(call) => call,
+            None => return Ok(false),
+        };
+        let result = match self.bindings.get_mut(&call.name) {
+            Some(binding) => (binding.callback)(call.args),
+            None => return Ok(false),
+        };
+
+        // This fails if the page navigated in the meantime, which is only logged
+        let params = ResolveCall {
+            expression: resolve_script(call.seq, &result)?,
+            silent: true,
+            context_id: call.context,
+        };
+        self.deferred.push(("Runtime.evaluate", serde_json::to_value(params)?));
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn console_event(ty: &str, text: &str) -> Event {
+        let json = format!(r#"{{"method": "Runtime.consoleAPICalled", "params": {{"type": {}, "executionContextId": 3,
+            "timestamp": 1.0, "args": [{{"type": "string", "value": {}}}]}}}}"#,
+            serde_json::to_string(ty).unwrap(), serde_json::to_string(text).unwrap());
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn decode_calls() {
+        let call = BindingCall::from_event(&console_event("debug",
+            r#"cxdbg:binding:{"name":"reportMetric","seq":4,"args":[1.5,"fps"]}"#)).unwrap();
+        assert_eq!(call.name, "reportMetric");
+        assert_eq!(call.seq, 4);
+        assert_eq!(call.args, serde_json::from_str::<Value>(r#"[1.5, "fps"]"#).unwrap());
+        assert_eq!(call.context, Some(Runtime::ExecutionContextId(3)));
+
+        assert_eq!(BindingCall::from_event(&console_event("log", r#"cxdbg:binding:{"name":"f","seq":1,"args":[]}"#)), None);
+        assert_eq!(BindingCall::from_event(&console_event("debug", "hello")), None);
+        assert_eq!(BindingCall::from_event(&console_event("debug", "cxdbg:binding:{")), None);
+    }
+
+    #[test]
+    fn scripts() {
+        let script = binding_script("report\"Metric").unwrap();
+        assert!(script.contains(r#"var name = "report\"Metric", prefix = "cxdbg:binding:";"#));
+        assert_eq!(resolve_script(2, &Value::from("ok")).unwrap(),
+            r#"(function(calls) { var resolve = calls.pending[2]; delete calls.pending[2]; resolve("ok"); })(window.__cxdbgCalls)"#);
+
+        let params = ResolveCall { expression: "1".to_string(), silent: true, context_id: Some(Runtime::ExecutionContextId(3)) };
+        assert_eq!(serde_json::to_string(&params).unwrap(), r#"{"expression":"1","silent":true,"contextId":3}"#);
+    }
+}
@@ -0,0 +1,295 @@
+//! Console messages and uncaught exceptions from the page
+
+use serde_json::{self, Value};
+
+use proto::{Event, Log, LogApi, Runtime};
+use runtime::JsException;
+use {DebugClient, Error};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ConsoleLevel {
+    Verbose,
+    Info,
+    Warning,
+    Error,
+}
+
+impl ConsoleLevel {
+    /// The level for a console API call type, e.g. `log` or `warning`
+    fn from_console_api(ty: &str) -> Self {
+        match ty {
+            "error" | "assert" => ConsoleLevel::Error,
+            "warning" => ConsoleLevel::Warning,
+            "debug" => ConsoleLevel::Verbose,
+            _ => ConsoleLevel::Info,
+        }
+    }
+
+    /// The level for a Log entry
+    fn from_log(level: &str) -> Self {
+        match level {
+            "error" => ConsoleLevel::Error,
+            "warning" => ConsoleLevel::Warning,
+            "verbose" => ConsoleLevel::Verbose,
+            _ => ConsoleLevel::Info,
+        }
+    }
+}
+
+/// A console API call, uncaught exception or browser log entry
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConsoleMessage {
+    pub level: ConsoleLevel,
+    /// `console-api` for console calls, `exception` for uncaught exceptions, or the Log
+    /// entry source, e.g. `network`
+    pub source: String,
+    pub text: String,
+    pub args: Vec<Runtime::RemoteObject>,
+    pub url: Option<String>,
+    pub line: Option<i64>,
+    pub stack: Option<Runtime::StackTrace>,
+    pub timestamp: f64,
+    exception_id: Option<i64>,
+}
+
+/// Format a value like the DevTools console, e.g. `{a: 1, b: "x"}` for objects
+pub fn format_remote_object(obj: &Runtime::RemoteObject) -> String {
+    if let Some(ref v) = obj._unserializableValue {
+        return serde_json::to_value(v).ok()
+            .and_then(|v| v.as_str().map(|s| s.to_string()))
+            .unwrap_or_default();
+    }
+    match (obj._type.as_str(), obj._subtype.as_deref()) {
+        ("undefined", _) => "undefined".to_string(),
+        ("object", Some("null")) => "null".to_string(),
+        ("string", _) => match obj._value {
+            Some(Value::String(ref s)) => s.clone(),
+            _ => String::new(),
+        },
+        _ => {
+            if let Some(ref preview) = obj._preview {
+                return format_preview(preview);
+            }
+            match (obj._value.as_ref(), obj._description.as_ref()) {
+                (Some(v), _) if !v.is_object() && !v.is_array() => v.to_string(),
+                (_, Some(d)) => d.clone(),
+                (Some(v), _) => v.to_string(),
+                _ => obj._type.clone(),
+            }
+        }
+    }
+}
+
+fn format_preview(preview: &Runtime::ObjectPreview) -> String {
+    let is_array = preview._subtype.as_ref().map(|s| s == "array").unwrap_or(false);
+    let mut parts: Vec<String> = preview._properties.iter()
+        .map(|p| {
+            let value = match (p._type.as_str(), p._value.as_ref(), p._valuePreview.as_ref()) {
+                (_, _, Some(nested)) => format_preview(nested),
+                ("string", Some(v), _) => format!("\"{}\"", v),
+                (_, Some(v), _) => v.clone(),
+                (ty, None, _) => ty.to_string(),
+            };
+            if is_array { value } else { format!("{}: {}", p._name, value) }
+        })
+        .collect();
+    if preview._overflow {
+        parts.push("…".to_string());
+    }
+
+    if is_array {
+        format!("[{}]", parts.join(", "))
+    } else {
+        let prefix = match preview._description {
+            Some(ref d) if d != "Object" => format!("{} ", d),
+            _ => String::new(),
+        };
+        format!("{}{{{}}}", prefix, parts.join(", "))
+    }
+}
+
+fn format_args(args: &[Runtime::RemoteObject]) -> String {
+    args.iter().map(format_remote_object).collect::<Vec<_>>().join(" ")
+}
+
+/// Collects console messages from Runtime and Log events
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConsoleCollector {
+    messages: Vec<ConsoleMessage>,
+}
+
+impl ConsoleCollector {
+    pub fn new() -> Self {
+        ConsoleCollector::default()
+    }
+
+    /// Update the collector from an event, other events are ignored
+    pub fn apply(&mut self, ev: &Event) {
+        let msg = match *ev {
+            Event::Runtime(Runtime::Event::consoleAPICalled { ref _type, ref args, ref timestamp, ref stackTrace, .. }) => {
+                let top = stackTrace.as_ref().and_then(|s| s._callFrames.first());
+                ConsoleMessage {
+                    level: ConsoleLevel::from_console_api(_type),
+                    source: "console-api".to_string(),
+                    text: format_args(args),
+                    args: args.clone(),
+                    url: top.map(|f| f._url.clone()),
+                    line: top.map(|f| f._lineNumber),
+                    stack: stackTrace.clone(),
+                    timestamp: timestamp.0,
+                    exception_id: None,
+                }
+            }
+            Event::Runtime(Runtime::Event::exceptionThrown { ref timestamp, ref exceptionDetails }) => {
+                let text = match exceptionDetails._exception {
+                    Some(_) => format!("{} {}", exceptionDetails._text, JsException::from(exceptionDetails.clone()).message),
+                    None => exceptionDetails._text.clone(),
+                };
+                ConsoleMessage {
+                    level: ConsoleLevel::Error,
+                    source: "exception".to_string(),
+                    text,
+                    args: exceptionDetails._exception.iter().cloned().collect(),
+                    url: exceptionDetails._url.clone(),
+                    line: Some(exceptionDetails._lineNumber),
+                    stack: exceptionDetails._stackTrace.clone(),
+                    timestamp: timestamp.0,
+                    exception_id: Some(exceptionDetails._exceptionId),
+                }
+            }
+            // A rejected promise got a handler after all
+            Event::Runtime(Runtime::Event::exceptionRevoked { exceptionId, .. }) => {
+                self.messages.retain(|m| m.exception_id != Some(exceptionId));
+                return;
+            }
+            Event::Log(Log::Event::entryAdded { ref entry }) => ConsoleMessage {
+                level: ConsoleLevel::from_log(&entry._level),
+                source: entry._source.clone(),
+                text: entry._text.clone(),
+                args: entry._args.clone().unwrap_or_default(),
+                url: entry._url.clone(),
+                line: entry._lineNumber,
+                stack: entry._stackTrace.clone(),
+                timestamp: entry._timestamp.0,
+                exception_id: None,
+            },
+            _ => return,
+        };
+        self.messages.push(msg);
+    }
+
+    pub fn extend(&mut self, events: &[Event]) {
+        for ev in events {
+            self.apply(ev);
+        }
+    }
+
+    pub fn messages(&self) -> &[ConsoleMessage] {
+        &self.messages
+    }
+
+    pub fn errors(&self) -> Vec<&ConsoleMessage> {
+        self.messages.iter().filter(|m| m.level == ConsoleLevel::Error).collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.messages.clear();
+    }
+
+    /// Panic if the page logged errors or threw uncaught exceptions
+    pub fn assert_no_errors(&self) {
+        let errors = self.errors();
+        if !errors.is_empty() {
+            let lines: Vec<String> = errors.iter()
+                .map(|m| match (m.url.as_ref(), m.line) {
+                    (Some(url), Some(line)) => format!("  {} ({}:{})", m.text, url, line + 1),
+                    _ => format!("  {}", m.text),
+                })
+                .collect();
+            panic!("The page reported {} error(s):\n{}", errors.len(), lines.join("\n"));
+        }
+    }
+}
+
+impl DebugClient {
+    /// Enable the Runtime and Log domains and create a collector, pass it the events that
+    /// follow with ConsoleCollector::apply
+    pub fn console_collector(&mut self) -> Result<ConsoleCollector, Error> {
+        self.enable_contexts()?;
+        LogApi::enable(self)?;
+        Ok(ConsoleCollector::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn object(json: &str) -> Runtime::RemoteObject {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn event(json: &str) -> Event {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn format_objects() {
+        assert_eq!(format_remote_object(&object(r#"{"type": "string", "value": "hi"}"#)), "hi");
+        assert_eq!(format_remote_object(&object(r#"{"type": "number", "value": 1.5, "description": "1.5"}"#)), "1.5");
+        assert_eq!(format_remote_object(&object(r#"{"type": "number", "unserializableValue": "NaN"}"#)), "NaN");
+        assert_eq!(format_remote_object(&object(r#"{"type": "undefined"}"#)), "undefined");
+        assert_eq!(format_remote_object(&object(r#"{"type": "object", "subtype": "null", "value": null}"#)), "null");
+        assert_eq!(format_remote_object(&object(r#"{"type": "object", "className": "Object", "description": "Object",
+            "preview": {"type": "object", "description": "Object", "overflow": false, "properties": [
+                {"name": "a", "type": "number", "value": "1"},
+                {"name": "b", "type": "string", "value": "x"}]}}"#)), r#"{a: 1, b: "x"}"#);
+        assert_eq!(format_remote_object(&object(r#"{"type": "object", "subtype": "array", "description": "Array(3)",
+            "preview": {"type": "object", "subtype": "array", "description": "Array(3)", "overflow": true, "properties": [
+                {"name": "0", "type": "number", "value": "1"}]}}"#)), "[1, …]");
+        assert_eq!(format_remote_object(&object(r#"{"type": "function", "description": "function f() {}"}"#)), "function f() {}");
+    }
+
+    #[test]
+    fn collect() {
+        let mut console = ConsoleCollector::new();
+        console.extend(&[
+            event(r#"{"method": "Runtime.consoleAPICalled", "params": {"type": "log", "executionContextId": 1,
+                "timestamp": 1.0, "args": [{"type": "string", "value": "count"}, {"type": "number", "value": 2}]}}"#),
+            event(r#"{"method": "Runtime.consoleAPICalled", "params": {"type": "warning", "executionContextId": 1,
+                "timestamp": 2.0, "args": [{"type": "string", "value": "careful"}],
+                "stackTrace": {"callFrames": [{"functionName": "", "scriptId": "1", "url": "http://x/a.js",
+                    "lineNumber": 4, "columnNumber": 0}]}}}"#),
+            event(r#"{"method": "Log.entryAdded", "params": {"entry": {"source": "network", "level": "error",
+                "text": "Failed to load resource", "timestamp": 3.0, "url": "http://x/missing.png"}}}"#),
+        ]);
+        let msgs = console.messages();
+        assert_eq!(msgs[0].text, "count 2");
+        assert_eq!(msgs[0].level, ConsoleLevel::Info);
+        assert_eq!((msgs[1].level, msgs[1].line), (ConsoleLevel::Warning, Some(4)));
+        assert_eq!(msgs[1].url, Some("http://x/a.js".to_string()));
+        assert_eq!(msgs[2].source, "network");
+        assert_eq!(console.errors().len(), 1);
+    }
+
+    #[test]
+    fn revoked_exceptions() {
+        let mut console = ConsoleCollector::new();
+        console.apply(&event(r#"{"method": "Runtime.exceptionThrown", "params": {"timestamp": 1.0,
+            "exceptionDetails": {"exceptionId": 7, "text": "Uncaught (in promise)", "lineNumber": 0, "columnNumber": 0,
+                "exception": {"type": "object", "subtype": "error", "description": "Error: nope\n    at x"}}}}"#));
+        assert_eq!(console.errors()[0].text, "Uncaught (in promise) Error: nope");
+
+        console.apply(&event(r#"{"method": "Runtime.exceptionRevoked", "params": {"reason": "Handler added to rejected promise", "exceptionId": 7}}"#));
+        console.assert_no_errors();
+    }
+
+    #[test]
+    #[should_panic(expected = "The page reported 1 error(s)")]
+    fn assert_errors() {
+        let mut console = ConsoleCollector::new();
+        console.apply(&event(r#"{"method": "Runtime.consoleAPICalled", "params": {"type": "error", "executionContextId": 1,
+            "timestamp": 1.0, "args": [{"type": "string", "value": "bad"}]}}"#));
+        console.assert_no_errors();
+    }
+}
@@ -0,0 +1,185 @@
+//! Execution contexts by frame and world
+
+use std::collections::HashMap;
+
+use serde::de::DeserializeOwned;
+
+use proto::{Event, Page, PageApi, Runtime, RuntimeApi};
+use {DebugClient, Error};
+
+/// An execution context reported by the Runtime domain
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContextInfo {
+    pub id: Runtime::ExecutionContextId,
+    pub frame_id: Option<Page::FrameId>,
+    /// The world name, empty for the main world
+    pub name: String,
+    pub origin: String,
+    /// The main world of the frame
+    pub is_default: bool,
+}
+
+/// The execution contexts in a page, kept up to date from Runtime events. This
+/// requires the Runtime domain to be enabled.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExecutionContexts {
+    contexts: HashMap<Runtime::ExecutionContextId, ContextInfo>,
+    main_frame: Option<Page::FrameId>,
+}
+
+impl ExecutionContexts {
+    pub fn new() -> Self {
+        ExecutionContexts::default()
+    }
+
+    /// Update the contexts from an event, other events are ignored
+    pub fn apply(&mut self, ev: &Event) {
+        match *ev {
+            Event::Runtime(Runtime::Event::executionContextCreated { ref context }) => {
+                let aux = context._auxData.as_ref();
+                let info = ContextInfo {
+                    id: context._id,
+                    frame_id: aux.and_then(|a| a["frameId"].as_str()).map(|id| Page::FrameId(id.to_string())),
+                    name: context._name.clone(),
+                    origin: context._origin.clone(),
+                    is_default: aux.and_then(|a| a["isDefault"].as_bool()).unwrap_or(false),
+                };
+                self.contexts.insert(context._id, info);
+            }
+            Event::Runtime(Runtime::Event::executionContextDestroyed { executionContextId }) => {
+                self.contexts.remove(&executionContextId);
+            }
+            Event::Runtime(Runtime::Event::executionContextsCleared) => self.contexts.clear(),
+            Event::Page(Page::Event::frameNavigated { ref frame }) if frame._parentId.is_none() => {
+                self.main_frame = Some(Page::FrameId(frame._id.clone()));
+            }
+            _ => (),
+        }
+    }
+
+    /// The main frame id, if it was seen in a Page event
+    pub fn main_frame(&self) -> Option<&Page::FrameId> {
+        self.main_frame.as_ref()
+    }
+
+    pub fn get(&self, id: Runtime::ExecutionContextId) -> Option<&ContextInfo> {
+        self.contexts.get(&id)
+    }
+
+    /// All contexts, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item=&ContextInfo> {
+        self.contexts.values()
+    }
+
+    /// The main world context of a frame
+    pub fn default_context(&self, frame_id: &Page::FrameId) -> Option<Runtime::ExecutionContextId> {
+        self.iter()
+            .find(|c| c.is_default && c.frame_id.as_ref() == Some(frame_id))
+            .map(|c| c.id)
+    }
+
+    /// The context of an isolated world in a frame
+    pub fn isolated_world(&self, frame_id: &Page::FrameId, name: &str) -> Option<Runtime::ExecutionContextId> {
+        self.iter()
+            .find(|c| !c.is_default && c.name == name && c.frame_id.as_ref() == Some(frame_id))
+            .map(|c| c.id)
+    }
+}
+
+impl DebugClient {
+    /// Enable the Runtime domain unless this client did before, enabling it reports the
+    /// existing contexts before it returns
+    pub(crate) fn enable_contexts(&mut self) -> Result<(), Error> {
+        if !self.runtime_enabled {
+            RuntimeApi::enable(self)?;
+            self.runtime_enabled = true;
+        }
+        Ok(())
+    }
+
+    /// The main frame id, the resource tree is only fetched if the registry has none with
+    /// a default context
+    fn main_frame_id(&mut self) -> Result<Page::FrameId, Error> {
+        self.enable_contexts()?;
+        if let Some(id) = self.contexts.main_frame.clone() {
+            if self.contexts.default_context(&id).is_some() {
+                return Ok(id);
+            }
+        }
+        // The main frame id changes on cross process navigations
+        let id = Page::FrameId(PageApi::getResourceTree(self)?.frameTree._frame._id);
+        self.contexts.main_frame = Some(id.clone());
+        Ok(id)
+    }
+
+    /// The default execution context of a frame, the Runtime domain is enabled if needed
+    pub fn frame_context(&mut self, frame_id: &Page::FrameId) -> Result<Runtime::ExecutionContextId, Error> {
+        self.enable_contexts()?;
+        self.contexts.default_context(frame_id)
+            .ok_or_else(|| Error::UnknownContext(format!("frame {}", frame_id)))
+    }
+
+    /// The default execution context of the main frame
+    pub fn main_context(&mut self) -> Result<Runtime::ExecutionContextId, Error> {
+        let frame_id = self.main_frame_id()?;
+        self.frame_context(&frame_id)
+    }
+
+    /// Evaluate an expression in the main world of a frame, like eval
+    pub fn evaluate_in_frame<T: DeserializeOwned>(&mut self, frame_id: &Page::FrameId, expr: &str) -> Result<T, Error> {
+        let context = self.frame_context(frame_id)?;
+        self.eval_in_context(Some(context), expr)
+    }
+
+    /// Evaluate an expression in an isolated world of the main frame, like eval. The
+    /// world is created if it does not exist.
+    pub fn evaluate_in_isolated_world<T: DeserializeOwned>(&mut self, name: &str, expr: &str) -> Result<T, Error> {
+        let frame_id = self.main_frame_id()?;
+        let context = match self.contexts.isolated_world(&frame_id, name) {
+            Some(id) => id,
+            None => PageApi::createIsolatedWorld(self, frame_id, Some(name.to_string()), None)?.executionContextId,
+        };
+        self.eval_in_context(Some(context), expr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    fn event(json: &str) -> Event {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn track_contexts() {
+        let f1 = Page::FrameId("F1".to_string());
+        let mut contexts = ExecutionContexts::new();
+        contexts.apply(&event(r#"{"method": "Runtime.executionContextCreated", "params": {"context": {
+            "id": 1, "origin": "http://x", "name": "", "auxData": {"isDefault": true, "frameId": "F1"}}}}"#));
+        contexts.apply(&event(r#"{"method": "Runtime.executionContextCreated", "params": {"context": {
+            "id": 2, "origin": "", "name": "tools", "auxData": {"isDefault": false, "frameId": "F1"}}}}"#));
+        contexts.apply(&event(r#"{"method": "Runtime.executionContextCreated", "params": {"context": {
+            "id": 3, "origin": "http://y", "name": "", "auxData": {"isDefault": true, "frameId": "F2"}}}}"#));
+
+        assert_eq!(contexts.default_context(&f1), Some(Runtime::ExecutionContextId(1)));
+        assert_eq!(contexts.isolated_world(&f1, "tools"), Some(Runtime::ExecutionContextId(2)));
+        assert_eq!(contexts.isolated_world(&f1, "other"), None);
+        assert_eq!(contexts.get(Runtime::ExecutionContextId(3)).unwrap().origin, "http://y");
+
+        contexts.apply(&event(r#"{"method": "Runtime.executionContextDestroyed", "params": {"executionContextId": 1}}"#));
+        assert_eq!(contexts.default_context(&f1), None);
+        assert_eq!(contexts.iter().count(), 2);
+
+        contexts.apply(&event(r#"{"method": "Runtime.executionContextsCleared"}"#));
+        assert_eq!(contexts.iter().count(), 0);
+
+        contexts.apply(&event(r#"{"method": "Page.frameNavigated", "params": {"frame": {
+            "id": "F3", "parentId": "F1", "loaderId": "L3", "url": "http://z/", "securityOrigin": "http://z", "mimeType": "text/html"}}}"#));
+        assert_eq!(contexts.main_frame(), None);
+        contexts.apply(&event(r#"{"method": "Page.frameNavigated", "params": {"frame": {
+            "id": "F1", "loaderId": "L4", "url": "http://x/", "securityOrigin": "http://x", "mimeType": "text/html"}}}"#));
+        assert_eq!(contexts.main_frame(), Some(&f1));
+    }
+}
@@ -0,0 +1,136 @@
+//! Automatic handling of JavaScript dialogs
+
+use serde_json;
+
+use proto::{Event, Page};
+use {DebugClient, Error};
+
+/// A JavaScript dialog (alert, confirm, prompt or onbeforeunload) opened by the page
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dialog {
+    pub url: String,
+    pub message: String,
+    pub dialog_type: Page::DialogType,
+    pub default_prompt: Option<String>,
+}
+
+impl Dialog {
+    pub(crate) fn from_event(ev: &Event) -> Option<Dialog> {
+        match *ev {
+            Event::Page(Page::Event::javascriptDialogOpening { ref url, ref message, ref _type, ref defaultPrompt }) => {
+                Some(Dialog {
+                    url: url.clone(),
+                    message: message.clone(),
+                    dialog_type: _type.clone(),
+                    default_prompt: defaultPrompt.clone(),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DialogAction {
+    /// Accept the dialog, with the text for prompts
+    Accept(Option<String>),
+    Dismiss,
+}
+
+/// How the client responds to JavaScript dialogs, requires the Page domain to be enabled
+pub enum DialogPolicy {
+    /// Dialogs are left open, use PageApi::handleJavaScriptDialog. This is the default.
+    Manual,
+    /// Accept all dialogs, prompts get their default text
+    Accept,
+    Dismiss,
+    /// Accept all dialogs, prompts are answered with the given text
+    Prompt(String),
+    Callback(Box<dyn FnMut(&Dialog) -> DialogAction + Send>),
+}
+
+impl DialogPolicy {
+    fn action(&mut self, dialog: &Dialog) -> Option<DialogAction> {
+        let is_prompt = dialog.dialog_type == Page::DialogType::_prompt;
+        match *self {
+            DialogPolicy::Manual => None,
+            DialogPolicy::Accept => Some(DialogAction::Accept(dialog.default_prompt.clone())),
+            DialogPolicy::Dismiss => Some(DialogAction::Dismiss),
+            DialogPolicy::Prompt(ref text) if is_prompt => Some(DialogAction::Accept(Some(text.clone()))),
+            DialogPolicy::Prompt(_) => Some(DialogAction::Accept(None)),
+            DialogPolicy::Callback(ref mut f) => Some(f(dialog)),
+        }
+    }
+}
+
+/// A dialog that was handled by the dialog policy
+#[derive(Debug, Clone, PartialEq)]
+pub struct HandledDialog {
+    pub dialog: Dialog,
+    pub action: DialogAction,
+}
+
+impl DebugClient {
+    /// Set how JavaScript dialogs are handled, handled dialogs are logged in handled_dialogs
+    pub fn set_dialog_policy(&mut self, policy: DialogPolicy) {
+        self.dialog_policy = policy;
+    }
+
+    /// Respond to a dialog event according to the dialog policy, the response is sent
+    /// after the event is handled
+    pub(crate) fn handle_dialog(&mut self, ev: &Event) -> Result<(), Error> {
+        let dialog = match Dialog::from_event(ev) {
+            Some(dialog) => dialog,
+            None => return Ok(()),
+        };
+        let action = match self.dialog_policy.action(&dialog) {
+            Some(action) => action,
+            None => return Ok(()),
+        };
+
+        let params = match action {
+            DialogAction::Accept(ref text) => Page::Request_handleJavaScriptDialog { _accept: true, _promptText: text.clone() },
+            DialogAction::Dismiss => Page::Request_handleJavaScriptDialog { _accept: false, _promptText: None },
+        };
+        self.deferred.push(("Page.handleJavaScriptDialog", serde_json::to_value(params)?));
+        self.handled_dialogs.push(HandledDialog { dialog, action });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    fn dialog(json: &str) -> Dialog {
+        let ev: Event = serde_json::from_str(json).unwrap();
+        Dialog::from_event(&ev).unwrap()
+    }
+
+    #[test]
+    fn policies() {
+        let alert = dialog(r#"{"method": "Page.javascriptDialogOpening", "params": {
+            "url": "http://x/", "message": "hi", "type": "alert"}}"#);
+        let prompt = dialog(r#"{"method": "Page.javascriptDialogOpening", "params": {
+            "url": "http://x/", "message": "name?", "type": "prompt", "defaultPrompt": "bob"}}"#);
+        assert_eq!(prompt.default_prompt, Some("bob".to_string()));
+
+        assert_eq!(DialogPolicy::Manual.action(&alert), None);
+        assert_eq!(DialogPolicy::Dismiss.action(&prompt), Some(DialogAction::Dismiss));
+        assert_eq!(DialogPolicy::Accept.action(&prompt), Some(DialogAction::Accept(Some("bob".to_string()))));
+
+        let mut answer = DialogPolicy::Prompt("alice".to_string());
+        assert_eq!(answer.action(&alert), Some(DialogAction::Accept(None)));
+        assert_eq!(answer.action(&prompt), Some(DialogAction::Accept(Some("alice".to_string()))));
+
+        let mut callback = DialogPolicy::Callback(Box::new(|d: &Dialog| {
+            if d.message == "hi" { DialogAction::Dismiss } else { DialogAction::Accept(None) }
+        }));
+        assert_eq!(callback.action(&alert), Some(DialogAction::Dismiss));
+        assert_eq!(callback.action(&prompt), Some(DialogAction::Accept(None)));
+
+        let ev: Event = serde_json::from_str(r#"{"method": "Page.loadEventFired", "params": {"timestamp": 1.0}}"#).unwrap();
+        assert_eq!(Dialog::from_event(&ev), None);
+    }
+}
@@ -0,0 +1,333 @@
+//! Handles for DOM nodes
+
+use std::cmp;
+use std::time::{Duration, Instant};
+
+use proto::{DOM, DOMApi, Runtime, RuntimeApi};
+use remote::RemoteObject;
+use runtime::{check_exception, from_remote_value, CallArg};
+use {DebugClient, Error};
+
+/// A DOM node known to the client. Node ids are only valid until the document is
+/// updated, e.g. by a navigation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ElementHandle {
+    node: DOM::NodeId,
+}
+
+/// How often the page checks visibility while waiting, style changes cause no mutations.
+/// Also the delay before waiting again, e.g. after a navigation.
+const VISIBILITY_INTERVAL_MS: u64 = 100;
+
+/// Resolves with the element once it meets the conditions, with null for a missing hidden
+/// element, or with "timeout". The visibility check is like has_area.
+const WAIT_FOR_SELECTOR: &str = r#"function(selector, visible, hidden, timeout, interval) {
+    function check() {
+        var el = document.querySelector(selector);
+        if (!visible && !hidden) return el;
+        var rect = el && el.getBoundingClientRect();
+        var shown = !!rect && rect.width > 0 && rect.height > 0;
+        if (visible) return shown ? el : undefined;
+        return shown ? undefined : el;
+    }
+    return new Promise(function(resolve) {
+        var observer, timer, poller;
+        function finish(result) {
+            if (observer) observer.disconnect();
+            clearTimeout(timer);
+            clearInterval(poller);
+            resolve(result);
+        }
+        function recheck() {
+            var result = check();
+            if (result !== undefined && (result !== null || hidden)) finish(result);
+        }
+        recheck();
+        observer = new MutationObserver(recheck);
+        observer.observe(document, {childList: true, subtree: true, attributes: true, characterData: true});
+        timer = setTimeout(function() { finish("timeout"); }, timeout);
+        if (visible || hidden) poller = setInterval(recheck, interval);
+    });
+}"#;
+
+/// Conditions for wait_for_selector
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WaitOptions {
+    /// Wait until the element is rendered with a non-empty box
+    pub visible: bool,
+    /// Wait until the element is missing or not rendered
+    pub hidden: bool,
+    /// Defaults to the client timeout
+    pub timeout: Option<Duration>,
+}
+
+impl WaitOptions {
+    /// Whether the wait is over, given whether a matching element is visible if there is one
+    fn satisfied(&self, visible: Option<bool>) -> bool {
+        match visible {
+            None => self.hidden,
+            Some(visible) if self.visible => visible,
+            Some(visible) if self.hidden => !visible,
+            Some(_) => true,
+        }
+    }
+}
+
+/// Whether a box model has an area, elements with `display: none` have no box model
+fn has_area(model: &DOM::BoxModel) -> bool {
+    model._width > 0 && model._height > 0
+}
+
+/// Whether waiting failed because of a navigation, then it continues in the new document.
+/// The old context is destroyed, and the new one may not be created yet.
+fn navigation_gap(e: &Error) -> bool {
+    matches!(*e, Error::CallError(_) | Error::UnknownContext(_))
+}
+
+/// Turn the flat name, value list of DOM.getAttributes into pairs
+fn attribute_pairs(flat: Vec<String>) -> Vec<(String, String)> {
+    let mut iter = flat.into_iter();
+    let mut pairs = Vec::new();
+    while let (Some(name), Some(value)) = (iter.next(), iter.next()) {
+        pairs.push((name, value));
+    }
+    pairs
+}
+
+impl ElementHandle {
+    pub fn new(node: DOM::NodeId) -> Self {
+        ElementHandle { node }
+    }
+
+    pub fn node_id(&self) -> DOM::NodeId {
+        self.node
+    }
+
+    /// The first descendant matching a CSS selector
+    pub fn query(&self, client: &mut DebugClient, selector: &str) -> Result<Option<ElementHandle>, Error> {
+        let node = DOMApi::querySelector(client, self.node, selector.to_string())?.nodeId;
+        // Node id 0 means there was no match
+        Ok(if node == DOM::NodeId(0) { None } else { Some(ElementHandle::new(node)) })
+    }
+
+    /// All descendants matching a CSS selector, in document order
+    pub fn query_all(&self, client: &mut DebugClient, selector: &str) -> Result<Vec<ElementHandle>, Error> {
+        let nodes = DOMApi::querySelectorAll(client, self.node, selector.to_string())?.nodeIds;
+        Ok(nodes.into_iter().map(ElementHandle::new).collect())
+    }
+
+    pub fn attribute(&self, client: &mut DebugClient, name: &str) -> Result<Option<String>, Error> {
+        Ok(self.attributes(client)?.into_iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value))
+    }
+
+    /// The attribute names and values, in source order
+    pub fn attributes(&self, client: &mut DebugClient) -> Result<Vec<(String, String)>, Error> {
+        Ok(attribute_pairs(DOMApi::getAttributes(client, self.node)?.attributes))
+    }
+
+    pub fn set_attribute(&self, client: &mut DebugClient, name: &str, value: &str) -> Result<(), Error> {
+        DOMApi::setAttributeValue(client, self.node, name.to_string(), value.to_string())?;
+        Ok(())
+    }
+
+    pub fn outer_html(&self, client: &mut DebugClient) -> Result<String, Error> {
+        Ok(DOMApi::getOuterHTML(client, Some(self.node), None, None)?.outerHTML)
+    }
+
+    /// The text content of the node and its descendants
+    pub fn text(&self, client: &mut DebugClient) -> Result<String, Error> {
+        let text = self.call(client, "function() { return this.textContent }")?;
+        from_remote_value::<Option<String>>(text.into_inner()).map(|t| t.unwrap_or_default())
+    }
+
+    /// Remove the node from the document
+    pub fn remove(self, client: &mut DebugClient) -> Result<(), Error> {
+        DOMApi::removeNode(client, self.node)?;
+        Ok(())
+    }
+
+    /// The content, padding, border and margin boxes, this fails for nodes that are not
+    /// rendered
+    pub fn box_model(&self, client: &mut DebugClient) -> Result<DOM::BoxModel, Error> {
+        Ok(DOMApi::getBoxModel(client, Some(self.node), None, None)?.model)
+    }
+
+    /// Whether the element is rendered with a non-empty box
+    pub fn is_visible(&self, client: &mut DebugClient) -> Result<bool, Error> {
+        match DOMApi::getBoxModel(client, Some(self.node), None, None) {
+            Ok(r) => Ok(has_area(&r.model)),
+            Err(Error::CallError(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The child elements, text and comment nodes are skipped
+    pub fn children(&self, client: &mut DebugClient) -> Result<Vec<ElementHandle>, Error> {
+        let list = self.call(client, "function() { return Array.from(this.children) }")?;
+        let mut items: Vec<(usize, RemoteObject)> = list.properties(client, true)?.into_iter()
+            .filter_map(|(name, obj)| name.parse().ok().map(|i| (i, obj)))
+            .collect();
+        items.sort_by_key(|&(i, _)| i);
+
+        let mut children = Vec::new();
+        for (_, obj) in items {
+            if let Some(handle) = request_node(client, &obj)? {
+                children.push(handle);
+            }
+        }
+        Ok(children)
+    }
+
+    /// The parent node, none for the document
+    pub fn parent(&self, client: &mut DebugClient) -> Result<Option<ElementHandle>, Error> {
+        let parent = self.call(client, "function() { return this.parentNode }")?;
+        request_node(client, &parent)
+    }
+
+    /// Call a function with the node as `this`
+    fn call(&self, client: &mut DebugClient, function: &str) -> Result<RemoteObject, Error> {
+        let obj = client.resolve_node(self.node, None)?;
+        obj.call_function_on(client, function, Vec::new())
+    }
+}
+
+/// The node for a JavaScript object, none for null
+fn request_node(client: &mut DebugClient, obj: &RemoteObject) -> Result<Option<ElementHandle>, Error> {
+    match obj.id() {
+        Some(id) => Ok(Some(ElementHandle::new(DOMApi::requestNode(client, id.clone())?.nodeId))),
+        None => Ok(None),
+    }
+}
+
+impl DebugClient {
+    /// The document node, the entry point for queries. The node is kept until the
+    /// document is updated, only then DOM.getDocument is called again, which invalidates
+    /// all node ids of the client, e.g. of element handles and a DomMirror.
+    pub fn document(&mut self) -> Result<ElementHandle, Error> {
+        if let Some(root) = self.document_root {
+            return Ok(ElementHandle::new(root));
+        }
+        // DOM.documentUpdated is only sent with the domain enabled
+        DOMApi::enable(self)?;
+        let root = DOMApi::getDocument(self, Some(0), None)?.root;
+        self.document_root = Some(root._nodeId);
+        Ok(ElementHandle::new(root._nodeId))
+    }
+
+    /// Wait until an element matching a CSS selector exists and meets the options. The
+    /// page waits with a MutationObserver, so no node ids are invalidated. Returns none
+    /// when waiting for a hidden element that is missing.
+    pub fn wait_for_selector(&mut self, selector: &str, opts: WaitOptions) -> Result<Option<ElementHandle>, Error> {
+        let deadline = Instant::now() + opts.timeout.unwrap_or(self.timeout);
+        let interval = Duration::from_millis(VISIBILITY_INTERVAL_MS);
+        loop {
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(Error::Timeout);
+            }
+            let remaining = deadline - now;
+            let timeout = remaining.as_secs() * 1000 + u64::from(remaining.subsec_millis());
+            let args = vec![
+                CallArg::value(&selector)?, CallArg::value(&opts.visible)?, CallArg::value(&opts.hidden)?,
+                CallArg::value(&timeout)?, CallArg::value(&VISIBILITY_INTERVAL_MS)?,
+            ];
+            let args = args.into_iter().map(Runtime::CallArgument::from).collect();
+
+            let r = self.main_context().and_then(|context| {
+                RuntimeApi::callFunctionOn(self, WAIT_FOR_SELECTOR.to_string(), None, Some(args),
+                    Some(true), None, None, None, Some(true), Some(context), None)
+            });
+            let r = match r {
+                Ok(r) => r,
+                Err(ref e) if navigation_gap(e) => {
+                    self.wait_retry(cmp::min(deadline, Instant::now() + interval))?;
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            check_exception(r.exceptionDetails)?;
+            if r.result._type == "string" {
+                return Err(Error::Timeout);
+            }
+
+            // Node ids can only be pushed for a document known to the client
+            self.document()?;
+            let obj = RemoteObject::new(self, r.result, None);
+            let element = match request_node(self, &obj)? {
+                Some(el) => el,
+                None => return Ok(None),
+            };
+            // The box model decides, the page check could be outdated by now
+            if !(opts.visible || opts.hidden) || opts.satisfied(Some(element.is_visible(self)?)) {
+                return Ok(Some(element));
+            }
+            self.wait_retry(cmp::min(deadline, Instant::now() + interval))?;
+        }
+    }
+
+    /// Handle events until a time passes
+    fn wait_retry(&mut self, until: Instant) -> Result<(), Error> {
+        while self.poll_until(until)? {}
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+    use proto::Page;
+    use ExecutionContexts;
+
+    #[test]
+    fn wait_conditions() {
+        let present = WaitOptions::default();
+        assert!(present.satisfied(Some(false)));
+        assert!(!present.satisfied(None));
+
+        let visible = WaitOptions { visible: true, ..WaitOptions::default() };
+        assert!(visible.satisfied(Some(true)));
+        assert!(!visible.satisfied(Some(false)));
+        assert!(!visible.satisfied(None));
+
+        let hidden = WaitOptions { hidden: true, ..WaitOptions::default() };
+        assert!(hidden.satisfied(None));
+        assert!(hidden.satisfied(Some(false)));
+        assert!(!hidden.satisfied(Some(true)));
+    }
+
+    #[test]
+    fn navigation_gaps() {
+        // Between the navigation and the new default context the main frame has none
+        let mut contexts = ExecutionContexts::new();
+        contexts.apply(&serde_json::from_str(r#"{"method": "Runtime.executionContextsCleared"}"#).unwrap());
+        let frame = Page::FrameId("F1".to_string());
+        assert_eq!(contexts.default_context(&frame), None);
+        assert!(navigation_gap(&Error::UnknownContext(format!("frame {}", frame))));
+
+        assert!(navigation_gap(&Error::CallError(None)));
+        assert!(!navigation_gap(&Error::Timeout));
+        assert!(!navigation_gap(&Error::Navigation("net::ERR_ABORTED".to_string())));
+    }
+
+    #[test]
+    fn box_area() {
+        let model: DOM::BoxModel = serde_json::from_str(r#"{"content": [0, 0, 10, 0, 10, 5, 0, 5],
+            "padding": [0, 0, 10, 0, 10, 5, 0, 5], "border": [0, 0, 10, 0, 10, 5, 0, 5],
+            "margin": [0, 0, 10, 0, 10, 5, 0, 5], "width": 10, "height": 5}"#).unwrap();
+        assert!(has_area(&model));
+        assert!(!has_area(&DOM::BoxModel { _width: 0, ..model }));
+    }
+
+    #[test]
+    fn attributes() {
+        let flat = vec!["id", "main", "class", "a b", "hidden", ""].into_iter().map(String::from).collect();
+        assert_eq!(attribute_pairs(flat), vec![
+            ("id".to_string(), "main".to_string()),
+            ("class".to_string(), "a b".to_string()),
+            ("hidden".to_string(), String::new()),
+        ]);
+    }
+}
@@ -0,0 +1,296 @@
+//! A model of the page frame hierarchy
+
+use std::collections::HashMap;
+
+use proto::{Event, Page, PageApi, Runtime};
+use {DebugClient, Error};
+
+/// A frame in the FrameTree
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameInfo {
+    /// The frame details, None for frames that were attached but not yet navigated
+    pub frame: Option<Page::Frame>,
+    pub parent: Option<Page::FrameId>,
+    pub children: Vec<Page::FrameId>,
+    pub loading: bool,
+    /// The default execution context, requires the Runtime domain to be enabled
+    pub execution_context: Option<Runtime::ExecutionContextId>,
+}
+
+impl FrameInfo {
+    fn new(parent: Option<Page::FrameId>) -> Self {
+        FrameInfo {
+            frame: None,
+            parent,
+            children: Vec::new(),
+            loading: false,
+            execution_context: None,
+        }
+    }
+}
+
+/// The frames in a page, initialized from Page.getResourceTree and kept up to date
+/// by applying Page and Runtime events
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FrameTree {
+    main: Option<Page::FrameId>,
+    frames: HashMap<Page::FrameId, FrameInfo>,
+}
+
+impl FrameTree {
+    pub fn from_resource_tree(tree: &Page::FrameResourceTree) -> Self {
+        let mut frames = FrameTree {
+            main: Some(Page::FrameId(tree._frame._id.clone())),
+            frames: HashMap::new(),
+        };
+        frames.insert_tree(tree, None);
+        frames
+    }
+
+    fn insert_tree(&mut self, tree: &Page::FrameResourceTree, parent: Option<Page::FrameId>) {
+        let id = Page::FrameId(tree._frame._id.clone());
+        let mut info = FrameInfo::new(parent);
+        info.frame = Some(tree._frame.clone());
+        if let Some(ref children) = tree._childFrames {
+            for child in children {
+                info.children.push(Page::FrameId(child._frame._id.clone()));
+                self.insert_tree(child, Some(id.clone()));
+            }
+        }
+        self.frames.insert(id, info);
+    }
+
+    /// Update the tree from an event, other events are ignored
+    pub fn apply(&mut self, ev: &Event) {
+        match *ev {
+            Event::Page(Page::Event::frameAttached { ref frameId, ref parentFrameId, .. }) => {
+                self.attach(frameId, parentFrameId);
+            }
+            Event::Page(Page::Event::frameNavigated { ref frame }) => self.navigated(frame),
+            Event::Page(Page::Event::frameDetached { ref frameId }) => self.detach(frameId),
+            Event::Page(Page::Event::frameStartedLoading { ref frameId }) => {
+                if let Some(info) = self.frames.get_mut(frameId) {
+                    info.loading = true;
+                }
+            }
+            Event::Page(Page::Event::frameStoppedLoading { ref frameId }) => {
+                if let Some(info) = self.frames.get_mut(frameId) {
+                    info.loading = false;
+                }
+            }
+            Event::Runtime(Runtime::Event::executionContextCreated { ref context }) => {
+                // Only the default context of a frame, not isolated worlds
+                let aux = match context._auxData {
+                    Some(ref aux) if aux["isDefault"].as_bool() == Some(true) => aux,
+                    _ => return,
+                };
+                if let Some(frame_id) = aux["frameId"].as_str() {
+                    if let Some(info) = self.frames.get_mut(&Page::FrameId(frame_id.to_string())) {
+                        info.execution_context = Some(context._id);
+                    }
+                }
+            }
+            Event::Runtime(Runtime::Event::executionContextDestroyed { executionContextId }) => {
+                for info in self.frames.values_mut() {
+                    if info.execution_context == Some(executionContextId) {
+                        info.execution_context = None;
+                    }
+                }
+            }
+            Event::Runtime(Runtime::Event::executionContextsCleared) => {
+                for info in self.frames.values_mut() {
+                    info.execution_context = None;
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn attach(&mut self, id: &Page::FrameId, parent: &Page::FrameId) {
+        if self.frames.contains_key(id) {
+            return;
+        }
+        if let Some(info) = self.frames.get_mut(parent) {
+            info.children.push(id.clone());
+        }
+        self.frames.insert(id.clone(), FrameInfo::new(Some(parent.clone())));
+    }
+
+    fn navigated(&mut self, frame: &Page::Frame) {
+        let id = Page::FrameId(frame._id.clone());
+        match frame._parentId {
+            Some(ref parent) => self.attach(&id, &Page::FrameId(parent.clone())),
+            None => {
+                // The main frame id changes on cross process navigations
+                if self.main.as_ref() != Some(&id) {
+                    if let Some(old) = self.main.take() {
+                        self.detach(&old);
+                    }
+                    self.main = Some(id.clone());
+                    self.frames.insert(id.clone(), FrameInfo::new(None));
+                }
+            }
+        }
+
+        // Child frames belong to the previous document
+        let children = match self.frames.get_mut(&id) {
+            Some(info) => {
+                info.frame = Some(frame.clone());
+                info.children.clone()
+            }
+            None => return,
+        };
+        for child in &children {
+            self.detach(child);
+        }
+    }
+
+    fn detach(&mut self, id: &Page::FrameId) {
+        let info = match self.frames.remove(id) {
+            Some(info) => info,
+            None => return,
+        };
+        for child in &info.children {
+            self.detach(child);
+        }
+        if let Some(parent) = info.parent.and_then(|p| self.frames.get_mut(&p)) {
+            parent.children.retain(|c| c != id);
+        }
+        if self.main.as_ref() == Some(id) {
+            self.main = None;
+        }
+    }
+
+    pub fn main_frame_id(&self) -> Option<&Page::FrameId> {
+        self.main.as_ref()
+    }
+
+    pub fn main_frame(&self) -> Option<&Page::Frame> {
+        self.main.as_ref().and_then(|id| self.frame(id))
+    }
+
+    pub fn get(&self, id: &Page::FrameId) -> Option<&FrameInfo> {
+        self.frames.get(id)
+    }
+
+    pub fn frame(&self, id: &Page::FrameId) -> Option<&Page::Frame> {
+        self.frames.get(id).and_then(|info| info.frame.as_ref())
+    }
+
+    /// The ids of all frames in the tree, in no particular order
+    pub fn frame_ids(&self) -> Vec<&Page::FrameId> {
+        self.frames.keys().collect()
+    }
+
+    pub fn children(&self, id: &Page::FrameId) -> &[Page::FrameId] {
+        self.frames.get(id).map(|info| &info.children[..]).unwrap_or(&[])
+    }
+
+    pub fn parent(&self, id: &Page::FrameId) -> Option<&Page::FrameId> {
+        self.frames.get(id).and_then(|info| info.parent.as_ref())
+    }
+
+    pub fn url(&self, id: &Page::FrameId) -> Option<&str> {
+        self.frame(id).map(|frame| frame._url.as_str())
+    }
+
+    pub fn is_loading(&self, id: &Page::FrameId) -> bool {
+        self.frames.get(id).map(|info| info.loading).unwrap_or(false)
+    }
+
+    pub fn execution_context(&self, id: &Page::FrameId) -> Option<Runtime::ExecutionContextId> {
+        self.frames.get(id).and_then(|info| info.execution_context)
+    }
+}
+
+impl DebugClient {
+    /// Get a snapshot of the page frames, apply later events with FrameTree::apply
+    pub fn frame_tree(&mut self) -> Result<FrameTree, Error> {
+        let tree = PageApi::getResourceTree(self)?.frameTree;
+        Ok(FrameTree::from_resource_tree(&tree))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    fn event(json: &str) -> Event {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn frame_id(id: &str) -> Page::FrameId {
+        Page::FrameId(id.to_string())
+    }
+
+    fn tree() -> FrameTree {
+        let tree: Page::FrameResourceTree = serde_json::from_str(r#"{
+            "frame": {"id": "F1", "loaderId": "L1", "url": "http://x/", "securityOrigin": "http://x", "mimeType": "text/html"},
+            "childFrames": [{
+                "frame": {"id": "F2", "parentId": "F1", "loaderId": "L2", "url": "http://y/", "securityOrigin": "http://y", "mimeType": "text/html"},
+                "resources": []
+            }],
+            "resources": []}"#).unwrap();
+        FrameTree::from_resource_tree(&tree)
+    }
+
+    #[test]
+    fn resource_tree() {
+        let tree = tree();
+        assert_eq!(tree.main_frame().unwrap()._url, "http://x/");
+        assert_eq!(tree.children(&frame_id("F1")), &[frame_id("F2")]);
+        assert_eq!(tree.parent(&frame_id("F2")), Some(&frame_id("F1")));
+        assert_eq!(tree.url(&frame_id("F2")), Some("http://y/"));
+        assert_eq!(tree.url(&frame_id("F3")), None);
+    }
+
+    #[test]
+    fn frame_events() {
+        let mut tree = tree();
+        tree.apply(&event(r#"{"method": "Page.frameAttached", "params": {"frameId": "F3", "parentFrameId": "F2"}}"#));
+        tree.apply(&event(r#"{"method": "Page.frameStartedLoading", "params": {"frameId": "F3"}}"#));
+        assert_eq!(tree.children(&frame_id("F2")), &[frame_id("F3")]);
+        assert!(tree.is_loading(&frame_id("F3")));
+        assert_eq!(tree.frame(&frame_id("F3")), None);
+
+        tree.apply(&event(r#"{"method": "Page.frameNavigated", "params": {"frame": {
+            "id": "F3", "parentId": "F2", "loaderId": "L3", "url": "http://z/", "securityOrigin": "http://z", "mimeType": "text/html"}}}"#));
+        tree.apply(&event(r#"{"method": "Page.frameStoppedLoading", "params": {"frameId": "F3"}}"#));
+        assert_eq!(tree.url(&frame_id("F3")), Some("http://z/"));
+        assert!(!tree.is_loading(&frame_id("F3")));
+
+        // Detaching a frame removes its children too
+        tree.apply(&event(r#"{"method": "Page.frameDetached", "params": {"frameId": "F2"}}"#));
+        assert!(tree.children(&frame_id("F1")).is_empty());
+        assert_eq!(tree.get(&frame_id("F3")), None);
+        assert_eq!(tree.frame_ids(), vec![&frame_id("F1")]);
+    }
+
+    #[test]
+    fn main_frame_navigation() {
+        let mut tree = tree();
+        tree.apply(&event(r#"{"method": "Page.frameNavigated", "params": {"frame": {
+            "id": "F1", "loaderId": "L4", "url": "http://w/", "securityOrigin": "http://w", "mimeType": "text/html"}}}"#));
+        assert_eq!(tree.main_frame().unwrap()._url, "http://w/");
+        assert_eq!(tree.frame_ids(), vec![&frame_id("F1")]);
+
+        tree.apply(&event(r#"{"method": "Page.frameNavigated", "params": {"frame": {
+            "id": "F9", "loaderId": "L5", "url": "http://v/", "securityOrigin": "http://v", "mimeType": "text/html"}}}"#));
+        assert_eq!(tree.main_frame_id(), Some(&frame_id("F9")));
+        assert_eq!(tree.frame_ids(), vec![&frame_id("F9")]);
+    }
+
+    #[test]
+    fn execution_contexts() {
+        let mut tree = tree();
+        tree.apply(&event(r#"{"method": "Runtime.executionContextCreated", "params": {"context": {
+            "id": 1, "origin": "http://x", "name": "", "auxData": {"isDefault": true, "frameId": "F1"}}}}"#));
+        tree.apply(&event(r#"{"method": "Runtime.executionContextCreated", "params": {"context": {
+            "id": 2, "origin": "http://x", "name": "isolated", "auxData": {"isDefault": false, "frameId": "F1"}}}}"#));
+        assert_eq!(tree.execution_context(&frame_id("F1")), Some(Runtime::ExecutionContextId(1)));
+
+        tree.apply(&event(r#"{"method": "Runtime.executionContextDestroyed", "params": {"executionContextId": 1}}"#));
+        assert_eq!(tree.execution_context(&frame_id("F1")), None);
+    }
+}
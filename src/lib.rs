@@ -8,6 +8,7 @@ use serde::de::{DeserializeOwned};
 use serde::Serialize;
 
 extern crate reqwest;
+extern crate base64;
 extern crate websocket;
 use websocket::{Message, OwnedMessage, ClientBuilder, WebSocketError};
 use websocket::client::sync::Client;
@@ -17,12 +18,28 @@ use websocket::stream::sync::TcpStream;
 extern crate log;
 
 use std::fmt::Debug;
+use std::collections::HashMap;
+use std::io;
+use std::mem;
+use std::time::{Duration, Instant};
 
 #[derive(Debug)]
 pub enum Error {
     WebSocket(WebSocketError),
     Json(serde_json::Error),
     CallError(Option<ErrorInfo>),
+    Validation(schema::ValidationError),
+    /// A helper did not complete within the client timeout
+    Timeout,
+    /// A navigation failed, holds the network error text
+    Navigation(String),
+    /// Binary data, e.g. a screenshot, was not valid base64
+    Base64(base64::DecodeError),
+    Io(io::Error),
+    /// Evaluated JavaScript code threw an exception
+    JsException(Box<JsException>),
+    /// No execution context was found, e.g. for a frame that was detached
+    UnknownContext(String),
 }
 
 impl From<WebSocketError> for Error {
@@ -37,11 +54,46 @@ impl From<serde_json::Error> for Error {
     }
 }
 
+impl From<base64::DecodeError> for Error {
+    fn from(e: base64::DecodeError) -> Self {
+        Error::Base64(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<schema::ValidationError> for Error {
+    fn from(e: schema::ValidationError) -> Self {
+        Error::Validation(e)
+    }
+}
+
 pub struct DebugClient {
     client: Client<TcpStream>,
     id: usize,
     pub pending_events: Vec<proto::Event>,
     pending_responses: Vec<Response<Value>>,
+    timeout: Duration,
+    dialog_policy: DialogPolicy,
+    pub handled_dialogs: Vec<HandledDialog>,
+    releases: remote::ReleaseQueue,
+    groups: usize,
+    /// Execution contexts, tracked once the Runtime domain is enabled
+    pub contexts: ExecutionContexts,
+    runtime_enabled: bool,
+    /// The document node from document or dom_mirror, until the document is updated
+    document_root: Option<proto::DOM::NodeId>,
+    bindings: HashMap<String, binding::Binding>,
+    /// The override from set_device_metrics, used to restore it
+    device_metrics: Option<proto::Emulation::Request_setDeviceMetricsOverride>,
+    /// Commands queued by event handlers, sent after the message is handled
+    deferred: Vec<(&'static str, Value)>,
+    /// Ids of sent commands whose responses are dropped
+    detached: Vec<usize>,
 }
 
 impl DebugClient {
@@ -67,6 +119,18 @@ impl DebugClient {
                 client,
                 pending_events: Vec::new(),
                 pending_responses: Vec::new(),
+                timeout: Duration::from_secs(30),
+                dialog_policy: DialogPolicy::Manual,
+                handled_dialogs: Vec::new(),
+                releases: Default::default(),
+                groups: 0,
+                contexts: ExecutionContexts::new(),
+                runtime_enabled: false,
+                document_root: None,
+                bindings: HashMap::new(),
+                device_metrics: None,
+                deferred: Vec::new(),
+                detached: Vec::new(),
             }
         } else {
             panic!("webSocketDebuggerUrl is not a string");
@@ -74,7 +138,10 @@ impl DebugClient {
 
     }
 
-    fn call<C: Serialize+Debug, R: DeserializeOwned>(&mut self, method: &str, params: C) -> Result<R, Error> {
+    /// Call a method with typed params, this is used by the generated api traits
+    pub fn call<C: Serialize+Debug, R: DeserializeOwned>(&mut self, method: &str, params: C) -> Result<R, Error> {
+        self.flush_releases()?;
+
         let reqid = self.id;
         self.id += 1;
         let r = Request {
@@ -87,24 +154,107 @@ impl DebugClient {
         self.client.send_message(&Message::text(raw))?;
 
         loop {
-            self.poll()?;
-
-            if let Some(resp) = self.pending_responses.pop() {
-                if resp.id == reqid {
-                    if let Some(result) = resp.result {
-                        let r = serde_json::from_value(result)?;
-                        return Ok(r);
-                    } else {
-                        return Err(Error::CallError(resp.error));
-                    }
+            // The response may have arrived while a previous command was waiting
+            if let Some(i) = self.pending_responses.iter().position(|r| r.id == reqid) {
+                let resp = self.pending_responses.remove(i);
+                if let Some(result) = resp.result {
+                    let r = serde_json::from_value(result)?;
+                    return Ok(r);
                 } else {
-                    self.pending_responses.push(resp);
+                    return Err(Error::CallError(resp.error));
                 }
             }
+
+            self.poll()?;
+        }
+    }
+
+    /// Send a command without waiting for the response, which is dropped when it arrives
+    fn send_detached(&mut self, method: &str, params: Value) -> Result<(), Error> {
+        let reqid = self.id;
+        self.id += 1;
+        let raw = serde_json::to_string(&Request { id: reqid, method, params })?;
+        debug!("--> {:#?}", raw);
+        self.client.send_message(&Message::text(raw))?;
+        self.detached.push(reqid);
+        Ok(())
+    }
+
+    /// Send the commands queued while handling events, e.g. to close dialogs. They are
+    /// not sent from the event handlers, which may run with a read timeout or while another
+    /// command waits for its response.
+    fn send_deferred(&mut self) -> Result<(), Error> {
+        for (method, params) in mem::take(&mut self.deferred) {
+            self.send_detached(method, params)?;
         }
+        Ok(())
     }
 
+    /// Send any protocol command, the result is parsed into the matching ReturnType variant
+    pub fn send(&mut self, cmd: &proto::Command) -> Result<proto::ReturnType, Error> {
+        let mut v = serde_json::to_value(cmd)?;
+        let params = v["params"].take();
+        let method = cmd.unknown_method().unwrap_or_else(|| cmd.method_name()).to_string();
+        let result: Value = self.call(&method, params)?;
+        Ok(cmd.parse_result(result)?)
+    }
+
+    /// Call a method by name. The params of methods in the bundled protocol schema are
+    /// validated before they are sent, other methods, e.g. of newer browsers or extension
+    /// domains, are passed through unchanged.
+    pub fn call_raw(&mut self, method: &str, params: Value) -> Result<Value, Error> {
+        if schema::command(method).is_some() {
+            schema::validate_params(method, &params)?;
+        }
+        let params = match params {
+            Value::Null => Value::Object(Default::default()),
+            params => params,
+        };
+        self.call(method, params)
+    }
+
+    /// Compare the protocol domains supported by the browser with the bundled schema
+    pub fn schema_mismatches(&mut self) -> Result<Vec<schema::Mismatch>, Error> {
+        let r = proto::SchemaApi::getDomains(self)?;
+        Ok(schema::compare_domains(&r.domains))
+    }
+
+    /// Set the timeout for helpers that wait for events, e.g. navigate_and_wait. The default
+    /// is 30 seconds.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Like poll, but gives up when the deadline passes. Returns false if no message
+    /// arrived before the deadline.
+    pub fn poll_until(&mut self, deadline: Instant) -> Result<bool, Error> {
+        let now = Instant::now();
+        if now >= deadline {
+            return Ok(false);
+        }
+
+        self.client.stream_ref().set_read_timeout(Some(deadline - now))
+            .map_err(WebSocketError::from)?;
+        let r = self.receive();
+        self.client.stream_ref().set_read_timeout(None)
+            .map_err(WebSocketError::from)?;
+        self.send_deferred()?;
+
+        match r {
+            Ok(()) => Ok(true),
+            Err(Error::WebSocket(WebSocketError::IoError(ref e)))
+                if e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Wait for a message and handle it
     pub fn poll(&mut self) -> Result<(), Error> {
+        self.receive()?;
+        self.send_deferred()
+    }
+
+    fn receive(&mut self) -> Result<(), Error> {
         let frame = self.client.recv_message()?;
 
         let v: Value = match frame {
@@ -114,13 +264,28 @@ impl DebugClient {
         debug!("<- {:#?}", v);
 
         if let Some(_) = v.get("id") {
-            let r = serde_json::from_value(v)?;
+            let r: Response<Value> = serde_json::from_value(v)?;
             debug!("<- {:#?}", r);
+            if let Some(i) = self.detached.iter().position(|&id| id == r.id) {
+                self.detached.remove(i);
+                if let Some(ref e) = r.error {
+                    warn!("Deferred command failed: {:?}", e);
+                }
+                return Ok(());
+            }
             self.pending_responses.push(r);
             Ok(())
         } else {
             let m = serde_json::from_value(v)?;
             debug!("<- {:#?}", m);
+            self.handle_dialog(&m)?;
+            if self.handle_binding_call(&m)? {
+                return Ok(());
+            }
+            self.contexts.apply(&m);
+            if let proto::Event::DOM(proto::DOM::Event::documentUpdated) = m {
+                self.document_root = None;
+            }
             self.pending_events.push(m);
             Ok(())
         }
@@ -132,6 +297,36 @@ pub mod proto {
     include!(concat!(env!("OUT_DIR"), "/proto.rs"));
 }
 
+pub mod schema;
+
+mod page;
+pub use page::{WaitUntil, Navigation, History};
+mod frames;
+pub use frames::{FrameTree, FrameInfo};
+mod screenshot;
+pub use screenshot::{ImageFormat, ScreenshotArea, ScreenshotOptions};
+mod pdf;
+pub use pdf::{Length, PaperSize, Margins, PdfOptions};
+mod screencast;
+pub use screencast::{Screencast, ScreencastFrame, ScreencastOptions};
+mod dialog;
+pub use dialog::{Dialog, DialogAction, DialogPolicy, HandledDialog};
+mod runtime;
+pub use runtime::{JsException, CallArg};
+mod remote;
+pub use remote::{RemoteObject, ObjectGroup};
+mod console;
+pub use console::{ConsoleCollector, ConsoleLevel, ConsoleMessage, format_remote_object};
+mod contexts;
+pub use contexts::{ContextInfo, ExecutionContexts};
+mod binding;
+mod scripts;
+pub use scripts::ScriptInjector;
+mod element;
+pub use element::{ElementHandle, WaitOptions};
+mod mirror;
+pub use mirror::{DomMirror, MirrorNode};
+
 /// A request message sent by the client
 #[derive(Serialize, Debug)]
 struct Request<'s, A> {
@@ -159,6 +354,18 @@ pub struct ErrorInfo {
 #[macro_use]
 extern crate test_logger;
 
+/// Compile the code generator snapshots from codegen/fixtures
+#[cfg(test)]
+#[allow(non_snake_case, non_camel_case_types, dead_code)]
+mod codegen_fixture {
+    include!("../codegen/fixtures/basic_proto.rs");
+
+    pub mod fixture_schema {
+        use schema::{DomainInfo, TypeInfo, CommandInfo, EventInfo, ParamInfo};
+        include!("../codegen/fixtures/basic_schema.rs");
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -173,4 +380,130 @@ mod tests {
         NetworkApi::enable(&mut c, None, None).unwrap();
         let doc = DOMApi::getDocument(&mut c, None, None).unwrap();
     });
+
+    #[test]
+    test!(event_domains, {
+        let ev: proto::Event = serde_json::from_str(
+            r#"{"method": "Page.loadEventFired", "params": {"timestamp": 1.5}}"#).unwrap();
+        assert_eq!(ev.domain(), "Page");
+        assert_eq!(ev.method_name(), "Page.loadEventFired");
+        match ev {
+            proto::Event::Page(proto::Page::Event::loadEventFired { timestamp }) => assert_eq!(timestamp.0, 1.5),
+            _ => panic!("Unexpected event {:?}", ev),
+        }
+
+        let ev: proto::Event = serde_json::from_str(
+            r#"{"method": "Inspector.targetCrashed", "params": {}}"#).unwrap();
+        assert_eq!(ev.method_name(), "Inspector.targetCrashed");
+
+        let ev: proto::Event = serde_json::from_str(
+            r#"{"method": "Nope.nothing", "params": {}}"#).unwrap();
+        assert_eq!(ev.domain(), "");
+        assert_eq!(ev.unknown_method(), Some("Nope.nothing"));
+        match ev {
+            proto::Event::Unknown(_) => (),
+            _ => panic!("Unexpected event {:?}", ev),
+        }
+    });
+
+    #[test]
+    test!(server_side_types, {
+        let cmd: proto::Command = serde_json::from_str(
+            r#"{"id": 3, "method": "Page.navigate", "params": {"url": "about:blank"}}"#).unwrap();
+        assert_eq!(cmd.method_name(), "Page.navigate");
+        match cmd {
+            proto::Command::Page(proto::Page::Command::navigate(ref req)) => assert_eq!(req._url, "about:blank"),
+            _ => panic!("Unexpected command {:?}", cmd),
+        }
+
+        let cmd: proto::Command = serde_json::from_str(
+            r#"{"id": 4, "method": "Page.enable"}"#).unwrap();
+        assert_eq!(cmd.domain(), "Page");
+
+        let ev = proto::Event::Page(proto::Page::Event::loadEventFired { timestamp: 2.0.into() });
+        let v = serde_json::to_value(&ev).unwrap();
+        let expected: Value = serde_json::from_str(
+            r#"{"method": "Page.loadEventFired", "params": {"timestamp": 2.0}}"#).unwrap();
+        assert_eq!(v, expected);
+    });
+
+    #[test]
+    test!(command_results, {
+        let cmd = proto::Command::Page(proto::Page::Command::navigate(proto::Page::Request_navigate {
+            _url: "about:blank".to_string(),
+            _referrer: None,
+            _transitionType: None,
+        }));
+        let v = serde_json::to_value(&cmd).unwrap();
+        assert_eq!(v["method"], "Page.navigate");
+        assert_eq!(v["params"]["url"], "about:blank");
+
+        let result: Value = serde_json::from_str(r#"{"frameId": "F1"}"#).unwrap();
+        match cmd.parse_result(result).unwrap() {
+            proto::ReturnType::Page(proto::Page::ReturnType::navigate(ret)) => assert_eq!(ret.frameId.0, "F1"),
+            ret => panic!("Unexpected result {:?}", ret),
+        }
+    });
+
+    #[test]
+    test!(identifier_newtypes, {
+        let id: proto::DOM::NodeId = serde_json::from_str("42").unwrap();
+        assert_eq!(id, proto::DOM::NodeId(42));
+        assert_eq!(id.to_string(), "42");
+        assert_eq!(serde_json::to_string(&id).unwrap(), "42");
+
+        let id = proto::Network::RequestId::from("1000.1".to_string());
+        assert_eq!(serde_json::to_string(&id).unwrap(), r#""1000.1""#);
+    });
+
+    #[test]
+    test!(generated_derives, {
+        let req = proto::Network::Request_enable::default();
+        assert_eq!(req._maxTotalBufferSize, None);
+        assert_eq!(serde_json::to_string(&req).unwrap(), "{}");
+
+        let cmd = proto::Command::Network(proto::Network::Command::enable(req));
+        assert_eq!(cmd.clone(), cmd);
+        assert_eq!(proto::Nothing::default(), proto::Nothing {});
+    });
+
+    #[test]
+    test!(codegen_fixture, {
+        use codegen_fixture::{Event, Tree};
+
+        let ev: Event = serde_json::from_str(r#"{"method": "Tree.ready"}"#).unwrap();
+        assert_eq!(ev, Event::Tree(Tree::Event::ready));
+
+        let opts = Tree::Options { _mode: Some(Tree::Mode::_fast_mode), ..Default::default() };
+        assert_eq!(serde_json::to_string(&opts).unwrap(), r#"{"mode":"fast-mode"}"#);
+        assert_eq!(codegen_fixture::fixture_schema::DOMAINS.len(), 2);
+    });
+
+    #[test]
+    test!(schema_validation, {
+        use schema::{validate_params, ValidationError};
+
+        let cmd = schema::command("Page.navigate").unwrap();
+        assert_eq!(cmd.parameters[0].name, "url");
+        assert!(!cmd.parameters[0].optional);
+        assert!(schema::event("Page.loadEventFired").is_some());
+
+        let params: Value = serde_json::from_str(r#"{"url": "about:blank", "transitionType": "link"}"#).unwrap();
+        assert_eq!(validate_params("Page.navigate", &params), Ok(()));
+        assert_eq!(validate_params("Page.enable", &Value::Null), Ok(()));
+        assert_eq!(validate_params("Page.nope", &Value::Null),
+                   Err(ValidationError::UnknownMethod("Page.nope".to_string())));
+        assert_eq!(validate_params("Page.navigate", &Value::Null),
+                   Err(ValidationError::MissingParam("url".to_string())));
+
+        let params: Value = serde_json::from_str(r#"{"url": 1}"#).unwrap();
+        assert_eq!(validate_params("Page.navigate", &params),
+                   Err(ValidationError::InvalidType { param: "url".to_string(), expected: "string".to_string() }));
+        let params: Value = serde_json::from_str(r#"{"url": "about:blank", "transitionType": "nope"}"#).unwrap();
+        assert_eq!(validate_params("Page.navigate", &params),
+                   Err(ValidationError::InvalidValue { param: "transitionType".to_string(), value: "nope".to_string() }));
+        let params: Value = serde_json::from_str(r#"{"nodeId": "1"}"#).unwrap();
+        assert_eq!(validate_params("DOM.getOuterHTML", &params),
+                   Err(ValidationError::InvalidType { param: "nodeId".to_string(), expected: "integer".to_string() }));
+    });
 }
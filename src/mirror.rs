@@ -0,0 +1,452 @@
+//! An in-memory copy of the page DOM
+
+use std::collections::HashMap;
+
+use proto::{DOM, DOMApi, Event, Page};
+use {DebugClient, Error};
+
+const ELEMENT_NODE: i64 = 1;
+const TEXT_NODE: i64 = 3;
+const CDATA_SECTION_NODE: i64 = 4;
+const COMMENT_NODE: i64 = 8;
+const DOCUMENT_NODE: i64 = 9;
+const DOCUMENT_TYPE_NODE: i64 = 10;
+
+/// Elements that have no closing tag
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source", "track", "wbr",
+];
+
+/// Elements whose text is not escaped
+const RAW_TEXT_ELEMENTS: &[&str] = &[
+    "script", "style", "xmp", "iframe", "noembed", "noframes", "plaintext",
+];
+
+/// A node in the DomMirror
+#[derive(Debug, Clone, PartialEq)]
+pub struct MirrorNode {
+    pub id: DOM::NodeId,
+    pub parent: Option<DOM::NodeId>,
+    pub node_type: i64,
+    pub node_name: String,
+    pub local_name: String,
+    pub node_value: String,
+    pub attributes: Vec<(String, String)>,
+    /// The known children, in document order
+    pub children: Vec<DOM::NodeId>,
+    /// The number of children, which may not all be known
+    pub child_count: Option<i64>,
+    pub shadow_roots: Vec<DOM::NodeId>,
+    pub shadow_root_type: Option<DOM::ShadowRootType>,
+    pub pseudo_elements: Vec<DOM::NodeId>,
+    pub pseudo_type: Option<DOM::PseudoType>,
+    /// The document of a frame owner element
+    pub content_document: Option<DOM::NodeId>,
+    pub template_content: Option<DOM::NodeId>,
+    pub frame_id: Option<Page::FrameId>,
+}
+
+impl MirrorNode {
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+
+    pub fn is_element(&self) -> bool {
+        self.node_type == ELEMENT_NODE
+    }
+
+    /// The lower case tag name for elements
+    fn tag(&self) -> String {
+        if self.local_name.is_empty() { self.node_name.to_lowercase() } else { self.local_name.clone() }
+    }
+}
+
+/// A copy of the page DOM, initialized from DOM.getDocument and kept up to date by
+/// applying DOM events
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DomMirror {
+    root: Option<DOM::NodeId>,
+    nodes: HashMap<DOM::NodeId, MirrorNode>,
+}
+
+impl DomMirror {
+    pub fn from_document(root: &DOM::Node) -> Self {
+        let mut mirror = DomMirror::default();
+        mirror.root = Some(mirror.insert(root, None));
+        mirror
+    }
+
+    /// Add a node and its subtree, returns the node id
+    fn insert(&mut self, node: &DOM::Node, parent: Option<DOM::NodeId>) -> DOM::NodeId {
+        let id = node._nodeId;
+        let flat = node._attributes.clone().unwrap_or_default();
+        let mut mirror = MirrorNode {
+            id,
+            parent,
+            node_type: node._nodeType,
+            node_name: node._nodeName.clone(),
+            local_name: node._localName.clone(),
+            node_value: node._nodeValue.clone(),
+            attributes: flat.chunks(2).filter(|c| c.len() == 2).map(|c| (c[0].clone(), c[1].clone())).collect(),
+            children: Vec::new(),
+            child_count: node._childNodeCount,
+            shadow_roots: Vec::new(),
+            shadow_root_type: node._shadowRootType.clone(),
+            pseudo_elements: Vec::new(),
+            pseudo_type: node._pseudoType.clone(),
+            content_document: None,
+            template_content: None,
+            frame_id: node._frameId.clone(),
+        };
+        let insert_all = |mirror: &mut DomMirror, nodes: &Option<Vec<DOM::Node>>| -> Vec<DOM::NodeId> {
+            nodes.iter().flat_map(|n| n.iter()).map(|n| mirror.insert(n, Some(id))).collect()
+        };
+        mirror.children = insert_all(self, &node._children);
+        mirror.shadow_roots = insert_all(self, &node._shadowRoots);
+        mirror.pseudo_elements = insert_all(self, &node._pseudoElements);
+        mirror.content_document = node._contentDocument.as_ref().map(|n| self.insert(n, Some(id)));
+        mirror.template_content = node._templateContent.as_ref().map(|n| self.insert(n, Some(id)));
+        if node._children.is_some() && mirror.child_count.is_none() {
+            mirror.child_count = Some(mirror.children.len() as i64);
+        }
+        self.nodes.insert(id, mirror);
+        id
+    }
+
+    /// Remove a node and its subtree, the parent is not updated
+    fn remove_subtree(&mut self, id: DOM::NodeId) {
+        let node = match self.nodes.remove(&id) {
+            Some(node) => node,
+            None => return,
+        };
+        let nested = node.children.iter()
+            .chain(&node.shadow_roots)
+            .chain(&node.pseudo_elements)
+            .chain(&node.content_document)
+            .chain(&node.template_content);
+        for &child in nested {
+            self.remove_subtree(child);
+        }
+    }
+
+    /// Update the mirror from an event, other events are ignored. After documentUpdated
+    /// the mirror is empty, use reload to fetch the new document.
+    pub fn apply(&mut self, ev: &Event) {
+        let ev = match *ev {
+            Event::DOM(ref ev) => ev,
+            _ => return,
+        };
+        match *ev {
+            DOM::Event::documentUpdated => {
+                self.root = None;
+                self.nodes.clear();
+            }
+            DOM::Event::setChildNodes { parentId, ref nodes } => {
+                let old = match self.nodes.get(&parentId) {
+                    Some(parent) => parent.children.clone(),
+                    None => return,
+                };
+                for child in old {
+                    self.remove_subtree(child);
+                }
+                let children: Vec<_> = nodes.iter().map(|n| self.insert(n, Some(parentId))).collect();
+                if let Some(parent) = self.nodes.get_mut(&parentId) {
+                    parent.child_count = Some(children.len() as i64);
+                    parent.children = children;
+                }
+            }
+            DOM::Event::attributeModified { nodeId, ref name, ref value } => {
+                if let Some(node) = self.nodes.get_mut(&nodeId) {
+                    match node.attributes.iter_mut().find(|(n, _)| n == name) {
+                        Some(attr) => attr.1 = value.clone(),
+                        None => node.attributes.push((name.clone(), value.clone())),
+                    }
+                }
+            }
+            DOM::Event::attributeRemoved { nodeId, ref name } => {
+                if let Some(node) = self.nodes.get_mut(&nodeId) {
+                    node.attributes.retain(|(n, _)| n != name);
+                }
+            }
+            DOM::Event::characterDataModified { nodeId, ref characterData } => {
+                if let Some(node) = self.nodes.get_mut(&nodeId) {
+                    node.node_value = characterData.clone();
+                }
+            }
+            DOM::Event::childNodeCountUpdated { nodeId, childNodeCount } => {
+                if let Some(node) = self.nodes.get_mut(&nodeId) {
+                    node.child_count = Some(childNodeCount);
+                }
+            }
+            DOM::Event::childNodeInserted { parentNodeId, previousNodeId, ref node } => {
+                if !self.nodes.contains_key(&parentNodeId) {
+                    return;
+                }
+                let id = self.insert(node, Some(parentNodeId));
+                if let Some(parent) = self.nodes.get_mut(&parentNodeId) {
+                    // A previous node id of 0 means the node was inserted first
+                    let index = parent.children.iter().position(|&c| c == previousNodeId).map(|i| i + 1).unwrap_or(0);
+                    parent.children.insert(index, id);
+                    parent.child_count = Some(parent.child_count.unwrap_or(0) + 1);
+                }
+            }
+            DOM::Event::childNodeRemoved { parentNodeId, nodeId } => {
+                if let Some(parent) = self.nodes.get_mut(&parentNodeId) {
+                    parent.children.retain(|&c| c != nodeId);
+                    parent.child_count = parent.child_count.map(|c| (c - 1).max(0));
+                }
+                self.remove_subtree(nodeId);
+            }
+            DOM::Event::shadowRootPushed { hostId, ref root } => {
+                if !self.nodes.contains_key(&hostId) {
+                    return;
+                }
+                let id = self.insert(root, Some(hostId));
+                if let Some(host) = self.nodes.get_mut(&hostId) {
+                    host.shadow_roots.push(id);
+                }
+            }
+            DOM::Event::shadowRootPopped { hostId, rootId } => {
+                if let Some(host) = self.nodes.get_mut(&hostId) {
+                    host.shadow_roots.retain(|&r| r != rootId);
+                }
+                self.remove_subtree(rootId);
+            }
+            DOM::Event::pseudoElementAdded { parentId, ref pseudoElement } => {
+                if !self.nodes.contains_key(&parentId) {
+                    return;
+                }
+                let id = self.insert(pseudoElement, Some(parentId));
+                if let Some(parent) = self.nodes.get_mut(&parentId) {
+                    parent.pseudo_elements.push(id);
+                }
+            }
+            DOM::Event::pseudoElementRemoved { parentId, pseudoElementId } => {
+                if let Some(parent) = self.nodes.get_mut(&parentId) {
+                    parent.pseudo_elements.retain(|&p| p != pseudoElementId);
+                }
+                self.remove_subtree(pseudoElementId);
+            }
+            _ => (),
+        }
+    }
+
+    /// Fetch the document again, e.g. after documentUpdated
+    pub fn reload(&mut self, client: &mut DebugClient) -> Result<(), Error> {
+        *self = client.dom_mirror()?;
+        Ok(())
+    }
+
+    /// The document node, none after documentUpdated
+    pub fn root(&self) -> Option<&MirrorNode> {
+        self.root.and_then(|id| self.nodes.get(&id))
+    }
+
+    pub fn get(&self, id: DOM::NodeId) -> Option<&MirrorNode> {
+        self.nodes.get(&id)
+    }
+
+    pub fn children(&self, id: DOM::NodeId) -> &[DOM::NodeId] {
+        self.nodes.get(&id).map(|node| &node.children[..]).unwrap_or(&[])
+    }
+
+    pub fn parent(&self, id: DOM::NodeId) -> Option<DOM::NodeId> {
+        self.nodes.get(&id).and_then(|node| node.parent)
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// The node and its descendants in document order, including frame documents but not
+    /// shadow roots, template contents or pseudo elements
+    pub fn descendants(&self, id: DOM::NodeId) -> Vec<&MirrorNode> {
+        let mut nodes = Vec::new();
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            if let Some(node) = self.nodes.get(&id) {
+                nodes.push(node);
+                stack.extend(node.children.iter().rev());
+                stack.extend(&node.content_document);
+            }
+        }
+        nodes
+    }
+
+    /// All nodes in the document matching a predicate, in document order
+    pub fn find<F: Fn(&MirrorNode) -> bool>(&self, pred: F) -> Vec<&MirrorNode> {
+        match self.root {
+            Some(root) => self.descendants(root).into_iter().filter(|n| pred(n)).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// The element with an id attribute
+    pub fn find_by_id(&self, id: &str) -> Option<&MirrorNode> {
+        self.find(|n| n.is_element() && n.attribute("id") == Some(id)).into_iter().next()
+    }
+
+    /// The elements with a tag name, compared case insensitively
+    pub fn find_by_tag(&self, tag: &str) -> Vec<&MirrorNode> {
+        self.find(|n| n.is_element() && n.tag().eq_ignore_ascii_case(tag))
+    }
+
+    /// The text of the node and its descendants, like textContent
+    pub fn text(&self, id: DOM::NodeId) -> String {
+        self.descendants(id).iter()
+            .filter(|n| n.node_type == TEXT_NODE || n.node_type == CDATA_SECTION_NODE)
+            .map(|n| n.node_value.as_str())
+            .collect()
+    }
+
+    /// Serialize the node and its known descendants as HTML, like outerHTML
+    pub fn to_html(&self, id: DOM::NodeId) -> String {
+        let mut html = String::new();
+        self.write_html(id, false, &mut html);
+        html
+    }
+
+    fn write_html(&self, id: DOM::NodeId, raw_text: bool, out: &mut String) {
+        let node = match self.nodes.get(&id) {
+            Some(node) => node,
+            None => return,
+        };
+        match node.node_type {
+            ELEMENT_NODE => {
+                let tag = node.tag();
+                out.push('<');
+                out.push_str(&tag);
+                for (name, value) in &node.attributes {
+                    out.push_str(&format!(" {}=\"{}\"", name, escape(value, true)));
+                }
+                out.push('>');
+                if VOID_ELEMENTS.contains(&tag.as_str()) {
+                    return;
+                }
+                let raw = RAW_TEXT_ELEMENTS.contains(&tag.as_str());
+                let children = node.template_content.as_ref().map(|t| self.children(*t)).unwrap_or(&node.children);
+                for &child in children {
+                    self.write_html(child, raw, out);
+                }
+                out.push_str(&format!("</{}>", tag));
+            }
+            TEXT_NODE if raw_text => out.push_str(&node.node_value),
+            TEXT_NODE => out.push_str(&escape(&node.node_value, false)),
+            CDATA_SECTION_NODE => out.push_str(&format!("<![CDATA[{}]]>", node.node_value)),
+            COMMENT_NODE => out.push_str(&format!("<!--{}-->", node.node_value)),
+            DOCUMENT_TYPE_NODE => out.push_str(&format!("<!DOCTYPE {}>", node.node_name)),
+            DOCUMENT_NODE => {
+                for &child in &node.children {
+                    self.write_html(child, false, out);
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+fn escape(s: &str, attribute: bool) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '\u{a0}' => escaped.push_str("&nbsp;"),
+            '"' if attribute => escaped.push_str("&quot;"),
+            '<' if !attribute => escaped.push_str("&lt;"),
+            '>' if !attribute => escaped.push_str("&gt;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+impl DebugClient {
+    /// Enable the DOM domain and get the whole document including frames and shadow
+    /// roots, apply later events with DomMirror::apply. Earlier node ids of the client
+    /// are invalidated.
+    pub fn dom_mirror(&mut self) -> Result<DomMirror, Error> {
+        DOMApi::enable(self)?;
+        let root = DOMApi::getDocument(self, Some(-1), Some(true))?.root;
+        self.document_root = Some(root._nodeId);
+        Ok(DomMirror::from_document(&root))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    fn event(json: &str) -> Event {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn node(id: i64) -> DOM::NodeId {
+        DOM::NodeId(id)
+    }
+
+    fn mirror() -> DomMirror {
+        let root: DOM::Node = serde_json::from_str(r##"{"nodeId": 1, "backendNodeId": 1, "nodeType": 9,
+            "nodeName": "#document", "localName": "", "nodeValue": "", "childNodeCount": 2, "children": [
+            {"nodeId": 2, "backendNodeId": 2, "nodeType": 10, "nodeName": "html", "localName": "", "nodeValue": ""},
+            {"nodeId": 3, "backendNodeId": 3, "nodeType": 1, "nodeName": "HTML", "localName": "html", "nodeValue": "",
+                "attributes": [], "children": [
+                {"nodeId": 4, "backendNodeId": 4, "nodeType": 1, "nodeName": "BODY", "localName": "body", "nodeValue": "",
+                    "attributes": ["class", "main"], "children": [
+                    {"nodeId": 5, "backendNodeId": 5, "nodeType": 1, "nodeName": "P", "localName": "p", "nodeValue": "",
+                        "attributes": ["id", "intro"], "children": [
+                        {"nodeId": 6, "backendNodeId": 6, "nodeType": 3, "nodeName": "#text", "localName": "", "nodeValue": "a < b"}]},
+                    {"nodeId": 7, "backendNodeId": 7, "nodeType": 1, "nodeName": "BR", "localName": "br", "nodeValue": "",
+                        "attributes": []}]}]}]}"##).unwrap();
+        DomMirror::from_document(&root)
+    }
+
+    #[test]
+    fn document() {
+        let mirror = mirror();
+        assert_eq!(mirror.len(), 7);
+        assert_eq!(mirror.root().unwrap().children, vec![node(2), node(3)]);
+        assert_eq!(mirror.parent(node(6)), Some(node(5)));
+        assert_eq!(mirror.find_by_id("intro").unwrap().id, node(5));
+        assert_eq!(mirror.find_by_tag("BR").len(), 1);
+        assert_eq!(mirror.text(node(4)), "a < b");
+        assert_eq!(mirror.to_html(node(1)),
+            r#"<!DOCTYPE html><html><body class="main"><p id="intro">a &lt; b</p><br></body></html>"#);
+    }
+
+    #[test]
+    fn mutations() {
+        let mut mirror = mirror();
+        mirror.apply(&event(r##"{"method": "DOM.childNodeInserted", "params": {"parentNodeId": 4, "previousNodeId": 5,
+            "node": {"nodeId": 8, "backendNodeId": 8, "nodeType": 8, "nodeName": "#comment", "localName": "", "nodeValue": "x"}}}"##));
+        mirror.apply(&event(r#"{"method": "DOM.attributeModified", "params": {"nodeId": 5, "name": "title", "value": "\"hi\""}}"#));
+        mirror.apply(&event(r#"{"method": "DOM.attributeRemoved", "params": {"nodeId": 4, "name": "class"}}"#));
+        mirror.apply(&event(r#"{"method": "DOM.characterDataModified", "params": {"nodeId": 6, "characterData": "c"}}"#));
+        assert_eq!(mirror.children(node(4)), &[node(5), node(8), node(7)]);
+        assert_eq!(mirror.to_html(node(4)), r#"<body><p id="intro" title="&quot;hi&quot;">c</p><!--x--><br></body>"#);
+
+        mirror.apply(&event(r#"{"method": "DOM.childNodeRemoved", "params": {"parentNodeId": 4, "nodeId": 5}}"#));
+        assert_eq!(mirror.get(node(6)), None);
+        assert_eq!(mirror.get(node(4)).unwrap().child_count, Some(2));
+
+        mirror.apply(&event(r##"{"method": "DOM.setChildNodes", "params": {"parentId": 4, "nodes": [
+            {"nodeId": 9, "backendNodeId": 9, "nodeType": 3, "nodeName": "#text", "localName": "", "nodeValue": "new"}]}}"##));
+        assert_eq!(mirror.to_html(node(4)), "<body>new</body>");
+        assert_eq!(mirror.get(node(7)), None);
+
+        mirror.apply(&event(r##"{"method": "DOM.shadowRootPushed", "params": {"hostId": 4, "root": {"nodeId": 10,
+            "backendNodeId": 10, "nodeType": 11, "nodeName": "#document-fragment", "localName": "", "nodeValue": "",
+            "shadowRootType": "open"}}}"##));
+        assert_eq!(mirror.get(node(4)).unwrap().shadow_roots, vec![node(10)]);
+        mirror.apply(&event(r#"{"method": "DOM.shadowRootPopped", "params": {"hostId": 4, "rootId": 10}}"#));
+        assert_eq!(mirror.get(node(10)), None);
+
+        mirror.apply(&event(r#"{"method": "DOM.documentUpdated"}"#));
+        assert!(mirror.root().is_none());
+        assert!(mirror.is_empty());
+    }
+}
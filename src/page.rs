@@ -0,0 +1,408 @@
+//! Page navigation helpers
+
+use std::cmp;
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+use proto::{Event, Network, Page, PageApi, NetworkApi, Nothing};
+use runtime::CallArg;
+use {DebugClient, Error};
+
+/// How often the history is checked while a same-document navigation commits
+const HISTORY_INTERVAL_MS: u64 = 50;
+
+/// Whether a history entry belongs to the current document according to the Navigation
+/// API, which knows pushState entries. Null if the API is missing or the entries differ.
+const SAME_DOCUMENT_ENTRY: &str = r#"function(offset, url) {
+    var nav = window.navigation;
+    if (!nav || !nav.currentEntry) return null;
+    var entry = nav.entries()[nav.currentEntry.index + offset];
+    return entry && entry.url === url ? entry.sameDocument : null;
+}"#;
+
+#[derive(Serialize, Debug)]
+struct SetLifecycleEventsEnabled {
+    enabled: bool,
+}
+
+/// The milestone navigate_and_wait waits for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WaitUntil {
+    DomContentLoaded,
+    Load,
+    /// At most `n` requests in flight for `quiet_ms` milliseconds
+    NetworkIdle(usize, u64),
+    FirstMeaningfulPaint,
+}
+
+/// The result of a navigation
+#[derive(Debug, Clone, PartialEq)]
+pub struct Navigation {
+    /// The frame after the navigation was committed
+    pub frame: Page::Frame,
+    /// The HTTP status of the document, if it was loaded over the network
+    pub status: Option<i64>,
+}
+
+/// Tracks the progress of a navigation from the events that follow it
+pub(crate) struct NavigationState {
+    frame_id: Page::FrameId,
+    document_request: Option<Network::RequestId>,
+    status: Option<i64>,
+    frame: Option<Page::Frame>,
+    dom_content_loaded: bool,
+    loaded: bool,
+    first_meaningful_paint: bool,
+    inflight: HashSet<Network::RequestId>,
+    last_activity: Instant,
+}
+
+impl NavigationState {
+    pub(crate) fn new(frame_id: Page::FrameId) -> Self {
+        NavigationState {
+            frame_id,
+            document_request: None,
+            status: None,
+            frame: None,
+            dom_content_loaded: false,
+            loaded: false,
+            first_meaningful_paint: false,
+            inflight: HashSet::new(),
+            last_activity: Instant::now(),
+        }
+    }
+
+    /// Update the state from an event, fails if the document could not be loaded
+    pub(crate) fn apply(&mut self, ev: &Event, now: Instant) -> Result<(), Error> {
+        match *ev {
+            Event::Network(Network::Event::requestWillBeSent { ref requestId, ref frameId, ref _type, .. }) => {
+                if self.frame.is_none() && _type == &Some(Page::ResourceType::_Document)
+                        && frameId.as_ref() == Some(&self.frame_id) {
+                    self.document_request = Some(requestId.clone());
+                }
+                self.inflight.insert(requestId.clone());
+                self.last_activity = now;
+            }
+            Event::Network(Network::Event::responseReceived { ref requestId, ref response, .. })
+                    if self.document_request.as_ref() == Some(requestId) => {
+                self.status = Some(response._status);
+            }
+            Event::Network(Network::Event::loadingFinished { ref requestId, .. }) => {
+                self.inflight.remove(requestId);
+                self.last_activity = now;
+            }
+            Event::Network(Network::Event::loadingFailed { ref requestId, ref errorText, .. }) => {
+                if self.document_request.as_ref() == Some(requestId) {
+                    return Err(Error::Navigation(errorText.clone()));
+                }
+                self.inflight.remove(requestId);
+                self.last_activity = now;
+            }
+            Event::Page(Page::Event::frameNavigated { ref frame }) if frame._id == self.frame_id.0 => {
+                self.frame = Some(frame.clone());
+            }
+            // Page milestones are only meaningful once the new document was committed
+            Event::Page(Page::Event::domContentEventFired { .. }) if self.frame.is_some() => {
+                self.dom_content_loaded = true;
+            }
+            Event::Page(Page::Event::loadEventFired { .. }) if self.frame.is_some() => {
+                self.loaded = true;
+            }
+            Event::Page(Page::Event::lifecycleEvent { ref name, .. }) if self.frame.is_some() => {
+                match name.as_str() {
+                    "DOMContentLoaded" => self.dom_content_loaded = true,
+                    "load" => self.loaded = true,
+                    "firstMeaningfulPaint" => self.first_meaningful_paint = true,
+                    _ => (),
+                }
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// The navigation result, once the milestone was reached
+    pub(crate) fn done(&self, until: WaitUntil, now: Instant) -> Option<Navigation> {
+        let reached = match until {
+            WaitUntil::DomContentLoaded => self.dom_content_loaded,
+            WaitUntil::Load => self.loaded,
+            WaitUntil::FirstMeaningfulPaint => self.first_meaningful_paint,
+            WaitUntil::NetworkIdle(n, quiet_ms) => {
+                self.inflight.len() <= n && now >= self.last_activity + Duration::from_millis(quiet_ms)
+            }
+        };
+
+        match self.frame {
+            Some(ref frame) if reached => Some(Navigation {
+                frame: frame.clone(),
+                status: self.status,
+            }),
+            _ => None,
+        }
+    }
+
+    /// When the state can change without new events, i.e. when the network becomes idle
+    fn next_deadline(&self, until: WaitUntil) -> Option<Instant> {
+        match until {
+            WaitUntil::NetworkIdle(n, quiet_ms) if self.inflight.len() <= n => {
+                Some(self.last_activity + Duration::from_millis(quiet_ms))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Whether two URLs only differ in the fragment
+fn same_document_url(a: &str, b: &str) -> bool {
+    let strip = |url: &str| url.split('#').next().unwrap_or("").to_string();
+    strip(a) == strip(b)
+}
+
+/// The session history of the page
+#[derive(Debug, Clone, PartialEq)]
+pub struct History {
+    pub current_index: usize,
+    pub entries: Vec<Page::NavigationEntry>,
+}
+
+impl History {
+    pub fn current(&self) -> Option<&Page::NavigationEntry> {
+        self.entries.get(self.current_index)
+    }
+
+    /// The entry `offset` steps away from the current one
+    pub fn entry(&self, offset: i64) -> Option<&Page::NavigationEntry> {
+        self.index(offset).map(|idx| &self.entries[idx])
+    }
+
+    fn index(&self, offset: i64) -> Option<usize> {
+        let idx = self.current_index as i64 + offset;
+        if idx < 0 || idx as usize >= self.entries.len() {
+            return None;
+        }
+        Some(idx as usize)
+    }
+
+    pub fn can_go_back(&self) -> bool {
+        self.entry(-1).is_some()
+    }
+
+    pub fn can_go_forward(&self) -> bool {
+        self.entry(1).is_some()
+    }
+}
+
+impl DebugClient {
+    fn enable_navigation_events(&mut self) -> Result<(), Error> {
+        PageApi::enable(self)?;
+        // Not in the bundled protocol, older browsers send lifecycle events anyway
+        match self.call::<_, Nothing>("Page.setLifecycleEventsEnabled", SetLifecycleEventsEnabled { enabled: true }) {
+            Ok(_) | Err(Error::CallError(_)) => (),
+            Err(e) => return Err(e),
+        }
+        NetworkApi::enable(self, None, None)?;
+        Ok(())
+    }
+
+    /// Wait for the navigation of `frame_id`, from the events after pending_events[seen]
+    fn wait_for_navigation(&mut self, frame_id: Page::FrameId, mut seen: usize, deadline: Instant, until: WaitUntil) -> Result<Navigation, Error> {
+        let mut state = NavigationState::new(frame_id);
+
+        loop {
+            let now = Instant::now();
+            for ev in &self.pending_events[seen..] {
+                state.apply(ev, now)?;
+            }
+            seen = self.pending_events.len();
+
+            if let Some(nav) = state.done(until, now) {
+                return Ok(nav);
+            }
+            if now >= deadline {
+                return Err(Error::Timeout);
+            }
+
+            let poll_deadline = state.next_deadline(until)
+                .map(|d| cmp::min(d, deadline))
+                .unwrap_or(deadline);
+            self.poll_until(poll_deadline)?;
+        }
+    }
+
+    /// Navigate the page to `url` and wait until the given milestone is reached, or the
+    /// client timeout expires. Events are left in pending_events.
+    pub fn navigate_and_wait(&mut self, url: &str, until: WaitUntil) -> Result<Navigation, Error> {
+        self.enable_navigation_events()?;
+
+        let deadline = Instant::now() + self.timeout;
+        let seen = self.pending_events.len();
+        let frame_id = PageApi::navigate(self, url.to_string(), None, None)?.frameId;
+        self.wait_for_navigation(frame_id, seen, deadline, until)
+    }
+
+    pub fn history(&mut self) -> Result<History, Error> {
+        let r = PageApi::getNavigationHistory(self)?;
+        Ok(History {
+            current_index: r.currentIndex as usize,
+            entries: r.entries,
+        })
+    }
+
+    /// Whether the history entry `offset` steps away belongs to the current document, e.g.
+    /// from pushState or a fragment change. Without the Navigation API only the URLs are
+    /// compared.
+    fn is_same_document(&mut self, history: &History, offset: i64) -> Result<bool, Error> {
+        let (current, target) = match (history.current(), history.entry(offset)) {
+            (Some(current), Some(target)) => (current, target),
+            _ => return Ok(false),
+        };
+        let args = vec![CallArg::value(&offset)?, CallArg::value(&target._url)?];
+        let same: Option<bool> = self.call_function_with(SAME_DOCUMENT_ENTRY, args)?;
+        Ok(same.unwrap_or_else(|| same_document_url(&current._url, &target._url)))
+    }
+
+    /// Wait until the history entry at `index` is the current one
+    fn wait_for_history_index(&mut self, index: usize, deadline: Instant) -> Result<(), Error> {
+        loop {
+            if self.history()?.current_index == index {
+                return Ok(());
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(Error::Timeout);
+            }
+            let next = cmp::min(deadline, now + Duration::from_millis(HISTORY_INTERVAL_MS));
+            while self.poll_until(next)? {}
+        }
+    }
+
+    /// Navigate to the history entry `offset` steps away from the current one, and wait
+    /// like navigate_and_wait. Returns None if there is no such entry.
+    ///
+    /// Entries of the current document, from pushState or a fragment change, load nothing,
+    /// so the wait ends when the entry is current and the milestone is ignored.
+    pub fn go_to_history_offset(&mut self, offset: i64, until: WaitUntil) -> Result<Option<Navigation>, Error> {
+        let history = self.history()?;
+        let (index, entry_id) = match history.index(offset) {
+            Some(index) => (index, history.entries[index]._id),
+            None => return Ok(None),
+        };
+        self.enable_navigation_events()?;
+        let same_document = self.is_same_document(&history, offset)?;
+
+        let deadline = Instant::now() + self.timeout;
+        if same_document {
+            PageApi::navigateToHistoryEntry(self, entry_id)?;
+            self.wait_for_history_index(index, deadline)?;
+            let frame = PageApi::getResourceTree(self)?.frameTree._frame;
+            return Ok(Some(Navigation { frame, status: None }));
+        }
+
+        let frame_id = Page::FrameId(PageApi::getResourceTree(self)?.frameTree._frame._id);
+        let seen = self.pending_events.len();
+        PageApi::navigateToHistoryEntry(self, entry_id)?;
+        self.wait_for_navigation(frame_id, seen, deadline, until).map(Some)
+    }
+
+    pub fn go_back(&mut self, until: WaitUntil) -> Result<Option<Navigation>, Error> {
+        self.go_to_history_offset(-1, until)
+    }
+
+    pub fn go_forward(&mut self, until: WaitUntil) -> Result<Option<Navigation>, Error> {
+        self.go_to_history_offset(1, until)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    fn event(json: &str) -> Event {
+        serde_json::from_str(json).unwrap()
+    }
+
+    const DOCUMENT_REQUEST: &str = r#"{"method": "Network.requestWillBeSent", "params": {
+        "requestId": "L1", "loaderId": "L1", "documentURL": "http://x/", "frameId": "F1", "type": "Document",
+        "request": {"url": "http://x/", "method": "GET", "headers": {}, "initialPriority": "VeryHigh", "referrerPolicy": "no-referrer"},
+        "timestamp": 1.0, "wallTime": 1.0, "initiator": {"type": "other"}}}"#;
+
+    const FRAME_NAVIGATED: &str = r#"{"method": "Page.frameNavigated", "params": {"frame": {
+        "id": "F1", "loaderId": "L1", "url": "http://x/", "securityOrigin": "http://x", "mimeType": "text/html"}}}"#;
+
+    #[test]
+    fn load_with_status() {
+        let now = Instant::now();
+        let mut state = NavigationState::new(Page::FrameId("F1".to_string()));
+        state.apply(&event(DOCUMENT_REQUEST), now).unwrap();
+        state.apply(&event(r#"{"method": "Network.responseReceived", "params": {
+            "requestId": "L1", "loaderId": "L1", "timestamp": 1.0, "type": "Document", "frameId": "F1",
+            "response": {"url": "http://x/", "status": 404, "statusText": "Not Found", "headers": {}, "mimeType": "text/html",
+                "connectionReused": false, "connectionId": 1, "encodedDataLength": 0, "securityState": "neutral"}}}"#), now).unwrap();
+
+        // A load event before the commit belongs to the previous document
+        state.apply(&event(r#"{"method": "Page.loadEventFired", "params": {"timestamp": 1.0}}"#), now).unwrap();
+        assert_eq!(state.done(WaitUntil::Load, now), None);
+
+        state.apply(&event(FRAME_NAVIGATED), now).unwrap();
+        state.apply(&event(r#"{"method": "Page.loadEventFired", "params": {"timestamp": 2.0}}"#), now).unwrap();
+        let nav = state.done(WaitUntil::Load, now).unwrap();
+        assert_eq!(nav.frame._url, "http://x/");
+        assert_eq!(nav.status, Some(404));
+        assert_eq!(state.done(WaitUntil::FirstMeaningfulPaint, now), None);
+    }
+
+    #[test]
+    fn document_error() {
+        let now = Instant::now();
+        let mut state = NavigationState::new(Page::FrameId("F1".to_string()));
+        state.apply(&event(DOCUMENT_REQUEST), now).unwrap();
+        let r = state.apply(&event(r#"{"method": "Network.loadingFailed", "params": {
+            "requestId": "L1", "timestamp": 1.0, "type": "Document", "errorText": "net::ERR_NAME_NOT_RESOLVED"}}"#), now);
+        match r {
+            Err(Error::Navigation(ref e)) => assert_eq!(e, "net::ERR_NAME_NOT_RESOLVED"),
+            r => panic!("Unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn network_idle() {
+        let start = Instant::now();
+        let mut state = NavigationState::new(Page::FrameId("F1".to_string()));
+        state.apply(&event(DOCUMENT_REQUEST), start).unwrap();
+        state.apply(&event(FRAME_NAVIGATED), start).unwrap();
+
+        let idle = WaitUntil::NetworkIdle(0, 500);
+        assert_eq!(state.next_deadline(idle), None);
+        assert!(state.done(WaitUntil::NetworkIdle(1, 500), start + Duration::from_millis(500)).is_some());
+
+        state.apply(&event(r#"{"method": "Network.loadingFinished", "params": {
+            "requestId": "L1", "timestamp": 2.0, "encodedDataLength": 10}}"#), start).unwrap();
+        assert_eq!(state.next_deadline(idle), Some(start + Duration::from_millis(500)));
+        assert!(state.done(idle, start + Duration::from_millis(100)).is_none());
+        assert!(state.done(idle, start + Duration::from_millis(500)).is_some());
+    }
+
+    #[test]
+    fn history_entries() {
+        let r: Page::ReturnType_getNavigationHistory = serde_json::from_str(r#"{"currentIndex": 1, "entries": [
+            {"id": 1, "url": "http://x/", "userTypedURL": "", "title": "x", "transitionType": "typed"},
+            {"id": 4, "url": "http://y/", "userTypedURL": "", "title": "y", "transitionType": "link"}]}"#).unwrap();
+        let history = History { current_index: r.currentIndex as usize, entries: r.entries };
+        assert_eq!(history.current().unwrap()._url, "http://y/");
+        assert_eq!(history.entry(-1).unwrap()._id, 1);
+        assert!(history.can_go_back());
+        assert!(!history.can_go_forward());
+        assert_eq!(history.entry(-2), None);
+        assert_eq!(history.index(-1), Some(0));
+        assert_eq!(history.index(1), None);
+    }
+
+    #[test]
+    fn same_document_entries() {
+        assert!(same_document_url("http://x/a#top", "http://x/a"));
+        assert!(same_document_url("http://x/a?q=1#one", "http://x/a?q=1#two"));
+        assert!(!same_document_url("http://x/a", "http://x/b#a"));
+        assert!(!same_document_url("http://x/a?q=1", "http://x/a?q=2"));
+    }
+}
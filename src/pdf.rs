@@ -0,0 +1,188 @@
+//! PDF generation helpers
+
+use std::fs;
+use std::path::Path;
+
+use base64;
+
+use proto::Page;
+use {DebugClient, Error};
+
+/// A length used for paper sizes and margins
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Length {
+    In(f64),
+    Cm(f64),
+    Mm(f64),
+    /// CSS pixels, 96 per inch
+    Px(f64),
+}
+
+impl Length {
+    pub fn inches(&self) -> f64 {
+        match *self {
+            Length::In(v) => v,
+            Length::Cm(v) => v / 2.54,
+            Length::Mm(v) => v / 25.4,
+            Length::Px(v) => v / 96.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PaperSize {
+    pub width: Length,
+    pub height: Length,
+}
+
+impl PaperSize {
+    pub const A3: PaperSize = PaperSize { width: Length::Mm(297.0), height: Length::Mm(420.0) };
+    pub const A4: PaperSize = PaperSize { width: Length::Mm(210.0), height: Length::Mm(297.0) };
+    pub const A5: PaperSize = PaperSize { width: Length::Mm(148.0), height: Length::Mm(210.0) };
+    pub const LETTER: PaperSize = PaperSize { width: Length::In(8.5), height: Length::In(11.0) };
+    pub const LEGAL: PaperSize = PaperSize { width: Length::In(8.5), height: Length::In(14.0) };
+    pub const TABLOID: PaperSize = PaperSize { width: Length::In(11.0), height: Length::In(17.0) };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Margins {
+    pub top: Length,
+    pub bottom: Length,
+    pub left: Length,
+    pub right: Length,
+}
+
+impl Margins {
+    /// The same margin on all sides
+    pub fn uniform(l: Length) -> Self {
+        Margins { top: l, bottom: l, left: l, right: l }
+    }
+}
+
+/// Options for print_to_pdf, fields left as None use the browser defaults
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PdfOptions {
+    pub landscape: Option<bool>,
+    pub display_header_footer: Option<bool>,
+    pub print_background: Option<bool>,
+    pub scale: Option<f64>,
+    pub paper: Option<PaperSize>,
+    pub margins: Option<Margins>,
+    /// e.g. `1-5, 8, 11-13`
+    pub page_ranges: Option<String>,
+    pub ignore_invalid_page_ranges: Option<bool>,
+    /// HTML template for the header, not supported by older browsers
+    pub header_template: Option<String>,
+    /// HTML template for the footer, not supported by older browsers
+    pub footer_template: Option<String>,
+}
+
+/// The parameters for Page.printToPDF, the bundled protocol lacks the templates
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct PrintToPdf {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    landscape: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    display_header_footer: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    print_background: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scale: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    paper_width: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    paper_height: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    margin_top: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    margin_bottom: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    margin_left: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    margin_right: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    page_ranges: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ignore_invalid_page_ranges: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    header_template: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    footer_template: Option<String>,
+}
+
+impl From<&PdfOptions> for PrintToPdf {
+    fn from(opts: &PdfOptions) -> Self {
+        PrintToPdf {
+            landscape: opts.landscape,
+            display_header_footer: opts.display_header_footer,
+            print_background: opts.print_background,
+            scale: opts.scale,
+            paper_width: opts.paper.map(|p| p.width.inches()),
+            paper_height: opts.paper.map(|p| p.height.inches()),
+            margin_top: opts.margins.map(|m| m.top.inches()),
+            margin_bottom: opts.margins.map(|m| m.bottom.inches()),
+            margin_left: opts.margins.map(|m| m.left.inches()),
+            margin_right: opts.margins.map(|m| m.right.inches()),
+            page_ranges: opts.page_ranges.clone(),
+            ignore_invalid_page_ranges: opts.ignore_invalid_page_ranges,
+            header_template: opts.header_template.clone(),
+            footer_template: opts.footer_template.clone(),
+        }
+    }
+}
+
+impl DebugClient {
+    /// Print the page as PDF and return the decoded document, this only works in
+    /// headless mode
+    pub fn print_to_pdf(&mut self, opts: &PdfOptions) -> Result<Vec<u8>, Error> {
+        let r: Page::ReturnType_printToPDF = self.call("Page.printToPDF", PrintToPdf::from(opts))?;
+        Ok(base64::decode(&r.data)?)
+    }
+
+    /// Print the page as PDF and write it to a file
+    pub fn print_to_pdf_file<P: AsRef<Path>>(&mut self, opts: &PdfOptions, path: P) -> Result<(), Error> {
+        let data = self.print_to_pdf(opts)?;
+        fs::write(path, data)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn units() {
+        assert_eq!(Length::Px(96.0).inches(), 1.0);
+        assert_eq!(Length::Cm(2.54).inches(), 1.0);
+        assert_eq!(Length::Mm(25.4).inches(), 1.0);
+        assert!((PaperSize::A4.width.inches() - 8.27).abs() < 0.01);
+    }
+
+    #[test]
+    fn request_params() {
+        let opts = PdfOptions {
+            landscape: Some(true),
+            paper: Some(PaperSize::LETTER),
+            margins: Some(Margins::uniform(Length::Px(48.0))),
+            footer_template: Some("<span class=pageNumber></span>".to_string()),
+            ..Default::default()
+        };
+        let v = serde_json::to_value(PrintToPdf::from(&opts)).unwrap();
+        let expected: serde_json::Value = serde_json::from_str(r#"{
+            "landscape": true,
+            "paperWidth": 8.5,
+            "paperHeight": 11.0,
+            "marginTop": 0.5,
+            "marginBottom": 0.5,
+            "marginLeft": 0.5,
+            "marginRight": 0.5,
+            "footerTemplate": "<span class=pageNumber></span>"}"#).unwrap();
+        assert_eq!(v, expected);
+
+        let v = serde_json::to_value(PrintToPdf::from(&PdfOptions::default())).unwrap();
+        assert_eq!(v.as_object().map(|o| o.len()), Some(0));
+    }
+}
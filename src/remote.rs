@@ -0,0 +1,202 @@
+//! Handles for JavaScript objects in the page
+
+use std::mem;
+use std::sync::{Arc, Mutex};
+
+use proto::{DOM, DOMApi, Page, PageApi, Runtime, RuntimeApi};
+use runtime::check_exception;
+use {DebugClient, Error};
+
+/// An object, group or injected script to release with the next command
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Release {
+    Object(Runtime::RemoteObjectId),
+    Group(String),
+    Script(Page::ScriptIdentifier),
+}
+
+pub(crate) type ReleaseQueue = Arc<Mutex<Vec<Release>>>;
+
+/// A reference to a JavaScript value in the page. Objects are released when the handle
+/// is dropped, with the next command sent by the client.
+#[derive(Debug)]
+pub struct RemoteObject {
+    object: Runtime::RemoteObject,
+    group: Option<String>,
+    releases: ReleaseQueue,
+}
+
+impl RemoteObject {
+    pub(crate) fn new(client: &DebugClient, object: Runtime::RemoteObject, group: Option<String>) -> Self {
+        RemoteObject {
+            object,
+            group,
+            releases: client.releases.clone(),
+        }
+    }
+
+    /// The object id, primitive values have none
+    pub fn id(&self) -> Option<&Runtime::RemoteObjectId> {
+        self.object._objectId.as_ref()
+    }
+
+    /// The protocol description of the value
+    pub fn object(&self) -> &Runtime::RemoteObject {
+        &self.object
+    }
+
+    pub fn group(&self) -> Option<&str> {
+        self.group.as_deref()
+    }
+
+    /// Call a function with this object as `this`, the result belongs to the same group
+    pub fn call_function_on(&self, client: &mut DebugClient, function: &str, args: Vec<Runtime::CallArgument>) -> Result<RemoteObject, Error> {
+        let r = RuntimeApi::callFunctionOn(client, function.to_string(), self.id().cloned(), Some(args),
+            None, None, None, None, Some(true), None, self.group.clone())?;
+        check_exception(r.exceptionDetails)?;
+        Ok(RemoteObject::new(client, r.result, self.group.clone()))
+    }
+
+    /// The properties of this object that have a value, accessors are skipped
+    pub fn properties(&self, client: &mut DebugClient, own: bool) -> Result<Vec<(String, RemoteObject)>, Error> {
+        let id = match self.id() {
+            Some(id) => id.clone(),
+            None => return Ok(Vec::new()),
+        };
+        let r = RuntimeApi::getProperties(client, id, Some(own), None, None)?;
+        check_exception(r.exceptionDetails)?;
+        Ok(r.result.into_iter()
+            .filter_map(|p| {
+                let name = p._name;
+                p._value.map(|v| (name, RemoteObject::new(client, v, self.group.clone())))
+            })
+            .collect())
+    }
+
+    /// Release the object now, rather than with the next command
+    pub fn release(mut self, client: &mut DebugClient) -> Result<(), Error> {
+        if let Some(id) = self.object._objectId.take() {
+            RuntimeApi::releaseObject(client, id)?;
+        }
+        Ok(())
+    }
+
+    /// Keep the object alive, it can still be released through its group
+    pub fn into_inner(mut self) -> Runtime::RemoteObject {
+        let object = self.object.clone();
+        self.object._objectId = None;
+        object
+    }
+}
+
+impl Drop for RemoteObject {
+    fn drop(&mut self) {
+        if let Some(id) = self.object._objectId.take() {
+            if let Ok(mut releases) = self.releases.lock() {
+                releases.push(Release::Object(id));
+            }
+        }
+    }
+}
+
+/// An object group, all objects in the group are released when this is dropped
+#[derive(Debug)]
+pub struct ObjectGroup {
+    name: String,
+    releases: ReleaseQueue,
+}
+
+impl ObjectGroup {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Drop for ObjectGroup {
+    fn drop(&mut self) {
+        if let Ok(mut releases) = self.releases.lock() {
+            releases.push(Release::Group(self.name.clone()));
+        }
+    }
+}
+
+impl DebugClient {
+    /// Create an object group with a unique name
+    pub fn object_group(&mut self) -> ObjectGroup {
+        self.groups += 1;
+        ObjectGroup {
+            name: format!("cxdbg-{}", self.groups),
+            releases: self.releases.clone(),
+        }
+    }
+
+    /// Like eval_handle, with the result in the given group
+    pub fn eval_handle_in(&mut self, expr: &str, group: &ObjectGroup) -> Result<RemoteObject, Error> {
+        let r = RuntimeApi::evaluate(self, expr.to_string(), Some(group.name.clone()), None, None, None,
+            None, None, None, Some(true))?;
+        check_exception(r.exceptionDetails)?;
+        Ok(RemoteObject::new(self, r.result, Some(group.name.clone())))
+    }
+
+    /// Get a handle for the JavaScript object of a DOM node
+    pub fn resolve_node(&mut self, node: DOM::NodeId, group: Option<&ObjectGroup>) -> Result<RemoteObject, Error> {
+        let group = group.map(|g| g.name.clone());
+        let object = DOMApi::resolveNode(self, Some(node), None, group.clone())?.object;
+        Ok(RemoteObject::new(self, object, group))
+    }
+
+    /// Release the objects, groups and scripts of dropped handles
+    pub(crate) fn flush_releases(&mut self) -> Result<(), Error> {
+        let pending = match self.releases.lock() {
+            Ok(mut releases) => mem::take(&mut *releases),
+            Err(_) => return Ok(()),
+        };
+        for release in pending {
+            let r = match release {
+                Release::Object(id) => RuntimeApi::releaseObject(self, id),
+                Release::Group(name) => RuntimeApi::releaseObjectGroup(self, name),
+                Release::Script(id) => PageApi::removeScriptToEvaluateOnNewDocument(self, id),
+            };
+            match r {
+                // The object may be gone already, e.g. after a navigation
+                Ok(_) | Err(Error::CallError(_)) => (),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    fn object(json: &str, group: Option<&str>, releases: &ReleaseQueue) -> RemoteObject {
+        RemoteObject {
+            object: serde_json::from_str(json).unwrap(),
+            group: group.map(|s| s.to_string()),
+            releases: releases.clone(),
+        }
+    }
+
+    #[test]
+    fn release_on_drop() {
+        let releases = ReleaseQueue::default();
+        let obj = object(r#"{"type": "object", "objectId": "{\"id\":1}"}"#, None, &releases);
+        let number = object(r#"{"type": "number", "value": 1}"#, None, &releases);
+        assert_eq!(number.id(), None);
+        drop(obj);
+        drop(number);
+
+        let group = ObjectGroup { name: "g".to_string(), releases: releases.clone() };
+        let kept = object(r#"{"type": "object", "objectId": "{\"id\":2}"}"#, Some("g"), &releases).into_inner();
+        assert!(kept._objectId.is_some());
+        drop(group);
+
+        assert_eq!(*releases.lock().unwrap(), vec![
+            Release::Object(Runtime::RemoteObjectId("{\"id\":1}".to_string())),
+            Release::Group("g".to_string()),
+        ]);
+    }
+}
@@ -0,0 +1,195 @@
+//! JavaScript evaluation helpers
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::{self, Value};
+
+use proto::{Runtime, RuntimeApi};
+use remote::RemoteObject;
+use {DebugClient, Error};
+
+/// A JavaScript exception thrown by evaluated code
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsException {
+    pub message: String,
+    pub url: Option<String>,
+    pub line: i64,
+    pub column: i64,
+    pub stack_trace: Option<Runtime::StackTrace>,
+}
+
+impl From<Runtime::ExceptionDetails> for JsException {
+    fn from(details: Runtime::ExceptionDetails) -> Self {
+        // The description of Error objects starts with the message and includes the stack
+        let message = match details._exception {
+            Some(Runtime::RemoteObject { _description: Some(ref d), .. }) => {
+                d.lines().next().unwrap_or("").to_string()
+            }
+            Some(Runtime::RemoteObject { _value: Some(Value::String(ref s)), .. }) => s.clone(),
+            Some(Runtime::RemoteObject { _value: Some(ref v), .. }) => v.to_string(),
+            _ => details._text.clone(),
+        };
+        JsException {
+            message,
+            url: details._url,
+            line: details._lineNumber,
+            column: details._columnNumber,
+            stack_trace: details._stackTrace,
+        }
+    }
+}
+
+/// An argument for call_function_with
+#[derive(Debug, Clone, PartialEq)]
+pub enum CallArg {
+    Value(Value),
+    Object(Runtime::RemoteObjectId),
+}
+
+impl CallArg {
+    pub fn value<T: Serialize>(v: &T) -> Result<CallArg, Error> {
+        Ok(CallArg::Value(serde_json::to_value(v)?))
+    }
+}
+
+impl From<&RemoteObject> for CallArg {
+    /// Primitive values have no object id and are passed by value
+    fn from(obj: &RemoteObject) -> Self {
+        match obj.id() {
+            Some(id) => CallArg::Object(id.clone()),
+            None => CallArg::Value(obj.object()._value.clone().unwrap_or(Value::Null)),
+        }
+    }
+}
+
+impl From<CallArg> for Runtime::CallArgument {
+    fn from(arg: CallArg) -> Self {
+        match arg {
+            CallArg::Value(v) => Runtime::CallArgument { _value: Some(v), _unserializableValue: None, _objectId: None },
+            CallArg::Object(id) => Runtime::CallArgument { _value: None, _unserializableValue: None, _objectId: Some(id) },
+        }
+    }
+}
+
+/// Serialized arguments, arrays and tuples are spread into separate arguments, `()` is
+/// no argument
+fn value_args<A: Serialize>(args: &A) -> Result<Vec<CallArg>, Error> {
+    Ok(match serde_json::to_value(args)? {
+        Value::Array(values) => values.into_iter().map(CallArg::Value).collect(),
+        Value::Null => Vec::new(),
+        v => vec![CallArg::Value(v)],
+    })
+}
+
+/// Turn the exception details of a Runtime result into an error
+pub(crate) fn check_exception(details: Option<Runtime::ExceptionDetails>) -> Result<(), Error> {
+    match details {
+        Some(details) => Err(Error::JsException(Box::new(details.into()))),
+        None => Ok(()),
+    }
+}
+
+/// Deserialize a value returned by value, undefined is treated as null
+pub(crate) fn from_remote_value<T: DeserializeOwned>(obj: Runtime::RemoteObject) -> Result<T, Error> {
+    Ok(serde_json::from_value(obj._value.unwrap_or(Value::Null))?)
+}
+
+impl DebugClient {
+    /// Evaluate an expression and deserialize the result, promises are awaited. Thrown
+    /// exceptions are returned as Error::JsException.
+    pub fn eval<T: DeserializeOwned>(&mut self, expr: &str) -> Result<T, Error> {
+        self.eval_in_context(None, expr)
+    }
+
+    pub(crate) fn eval_in_context<T: DeserializeOwned>(&mut self, context: Option<Runtime::ExecutionContextId>, expr: &str) -> Result<T, Error> {
+        let r = RuntimeApi::evaluate(self, expr.to_string(), None, None, None, context,
+            Some(true), None, None, Some(true))?;
+        check_exception(r.exceptionDetails)?;
+        from_remote_value(r.result)
+    }
+
+    /// Evaluate an expression and return a handle to the result, promises are awaited.
+    pub fn eval_handle(&mut self, expr: &str) -> Result<RemoteObject, Error> {
+        let r = RuntimeApi::evaluate(self, expr.to_string(), None, None, None, None,
+            None, None, None, Some(true))?;
+        check_exception(r.exceptionDetails)?;
+        Ok(RemoteObject::new(self, r.result, None))
+    }
+
+    /// Call a function in the page with serialized arguments and deserialize the result,
+    /// promises are awaited. A tuple, array or Vec is passed as separate arguments, e.g.
+    /// `call_function::<_, i64>("function(a, b) { return a + b }", (1, 2))`, wrap a single
+    /// array or a null as `(value,)`, `()` passes no arguments.
+    pub fn call_function<A: Serialize, R: DeserializeOwned>(&mut self, function: &str, args: A) -> Result<R, Error> {
+        let args = value_args(&args)?;
+        self.call_function_with(function, args)
+    }
+
+    /// Like call_function, but arguments may also be remote objects. The function runs in
+    /// the main frame.
+    pub fn call_function_with<R: DeserializeOwned>(&mut self, function: &str, args: Vec<CallArg>) -> Result<R, Error> {
+        let context = self.main_context()?;
+        let args = args.into_iter().map(Runtime::CallArgument::from).collect();
+        let r = RuntimeApi::callFunctionOn(self, function.to_string(), None, Some(args),
+            None, Some(true), None, None, Some(true), Some(context), None)?;
+        check_exception(r.exceptionDetails)?;
+        from_remote_value(r.result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exception(json: &str) -> JsException {
+        let details: Runtime::ExceptionDetails = serde_json::from_str(json).unwrap();
+        details.into()
+    }
+
+    #[test]
+    fn exception_messages() {
+        let e = exception(r#"{"exceptionId": 1, "text": "Uncaught", "lineNumber": 2, "columnNumber": 7,
+            "url": "http://x/a.js",
+            "exception": {"type": "object", "subtype": "error", "className": "Error",
+                "description": "Error: boom\n    at f (http://x/a.js:3:8)"},
+            "stackTrace": {"callFrames": [{"functionName": "f", "scriptId": "12", "url": "http://x/a.js",
+                "lineNumber": 2, "columnNumber": 7}]}}"#);
+        assert_eq!(e.message, "Error: boom");
+        assert_eq!((e.line, e.column), (2, 7));
+        assert_eq!(e.url, Some("http://x/a.js".to_string()));
+        assert_eq!(e.stack_trace.unwrap()._callFrames[0]._functionName, "f");
+
+        let e = exception(r#"{"exceptionId": 2, "text": "Uncaught", "lineNumber": 0, "columnNumber": 0,
+            "exception": {"type": "string", "value": "oops"}}"#);
+        assert_eq!(e.message, "oops");
+
+        let e = exception(r#"{"exceptionId": 3, "text": "Uncaught SyntaxError: Unexpected token )",
+            "lineNumber": 0, "columnNumber": 3}"#);
+        assert_eq!(e.message, "Uncaught SyntaxError: Unexpected token )");
+    }
+
+    #[test]
+    fn remote_values() {
+        let obj: Runtime::RemoteObject = serde_json::from_str(r#"{"type": "object", "value": {"a": [1, 2]}}"#).unwrap();
+        let v: ::std::collections::HashMap<String, Vec<i64>> = from_remote_value(obj).unwrap();
+        assert_eq!(v["a"], vec![1, 2]);
+
+        let undefined: Runtime::RemoteObject = serde_json::from_str(r#"{"type": "undefined"}"#).unwrap();
+        assert_eq!(from_remote_value::<Option<i64>>(undefined).unwrap(), None);
+    }
+
+    #[test]
+    fn call_args() {
+        assert_eq!(value_args(&(1, "a")).unwrap(), vec![
+            CallArg::Value(Value::from(1)),
+            CallArg::Value(Value::from("a")),
+        ]);
+        assert_eq!(value_args(&(vec![1, 2],)).unwrap(), vec![CallArg::Value(Value::from(vec![1, 2]))]);
+        assert_eq!(value_args(&true).unwrap(), vec![CallArg::Value(Value::from(true))]);
+        assert_eq!(value_args(&()).unwrap(), vec![]);
+        assert_eq!(value_args(&(Value::Null,)).unwrap(), vec![CallArg::Value(Value::Null)]);
+
+        let arg: Runtime::CallArgument = CallArg::Object(Runtime::RemoteObjectId("1".to_string())).into();
+        assert_eq!(serde_json::to_string(&arg).unwrap(), r#"{"objectId":"1"}"#);
+    }
+}
@@ -0,0 +1,225 @@
+//! Protocol metadata generated from src/chrome_protocol.json
+//!
+//! This can be used to list the available methods, show their documentation, or validate
+//! parameters before sending them to the browser.
+
+use serde_json::Value;
+use proto;
+
+/// A protocol domain, e.g. `Page`
+#[derive(Debug)]
+pub struct DomainInfo {
+    pub name: &'static str,
+    pub description: Option<&'static str>,
+    pub experimental: bool,
+    pub types: &'static [TypeInfo],
+    pub commands: &'static [CommandInfo],
+    pub events: &'static [EventInfo],
+}
+
+/// A type declared by a domain
+#[derive(Debug)]
+pub struct TypeInfo {
+    pub id: &'static str,
+    /// The schema type, e.g. `string` or `object`
+    pub ty: &'static str,
+    /// The element type for arrays
+    pub items: Option<&'static str>,
+    pub enum_values: &'static [&'static str],
+    pub properties: &'static [ParamInfo],
+    pub description: Option<&'static str>,
+}
+
+#[derive(Debug)]
+pub struct CommandInfo {
+    pub name: &'static str,
+    pub description: Option<&'static str>,
+    pub experimental: bool,
+    pub parameters: &'static [ParamInfo],
+    pub returns: &'static [ParamInfo],
+}
+
+#[derive(Debug)]
+pub struct EventInfo {
+    pub name: &'static str,
+    pub description: Option<&'static str>,
+    pub experimental: bool,
+    pub parameters: &'static [ParamInfo],
+}
+
+/// A command parameter, return value, event parameter or object property
+#[derive(Debug)]
+pub struct ParamInfo {
+    pub name: &'static str,
+    /// Either a primitive schema type (`string`, `integer`, `number`, `boolean`, `array`,
+    /// `object`, `any`) or a qualified reference to a domain type, e.g. `DOM.NodeId`
+    pub ty: &'static str,
+    /// The element type for arrays
+    pub items: Option<&'static str>,
+    pub optional: bool,
+    pub enum_values: &'static [&'static str],
+    pub description: Option<&'static str>,
+}
+
+include!(concat!(env!("OUT_DIR"), "/schema.rs"));
+
+impl DomainInfo {
+    pub fn command(&self, name: &str) -> Option<&'static CommandInfo> {
+        self.commands.iter().find(|c| c.name == name)
+    }
+
+    pub fn event(&self, name: &str) -> Option<&'static EventInfo> {
+        self.events.iter().find(|e| e.name == name)
+    }
+
+    pub fn type_info(&self, id: &str) -> Option<&'static TypeInfo> {
+        self.types.iter().find(|t| t.id == id)
+    }
+}
+
+pub fn domain(name: &str) -> Option<&'static DomainInfo> {
+    DOMAINS.iter().find(|d| d.name == name)
+}
+
+/// Find a command by its method name, e.g. `Page.navigate`
+pub fn command(method: &str) -> Option<&'static CommandInfo> {
+    let (domain_name, name) = split_method(method)?;
+    domain(domain_name)?.command(name)
+}
+
+/// Find an event by its method name, e.g. `Page.loadEventFired`
+pub fn event(method: &str) -> Option<&'static EventInfo> {
+    let (domain_name, name) = split_method(method)?;
+    domain(domain_name)?.event(name)
+}
+
+/// Find a type by its qualified name, e.g. `DOM.NodeId`
+pub fn type_info(qualified_id: &str) -> Option<&'static TypeInfo> {
+    let (domain_name, id) = split_method(qualified_id)?;
+    domain(domain_name)?.type_info(id)
+}
+
+fn split_method(method: &str) -> Option<(&str, &str)> {
+    let mut parts = method.splitn(2, '.');
+    match (parts.next(), parts.next()) {
+        (Some(domain), Some(name)) => Some((domain, name)),
+        _ => None,
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ValidationError {
+    UnknownMethod(String),
+    /// The params are not a JSON object
+    InvalidParams,
+    UnknownParam(String),
+    MissingParam(String),
+    /// A parameter does not match the schema type
+    InvalidType { param: String, expected: String },
+    /// A parameter is not one of the allowed enum values
+    InvalidValue { param: String, value: String },
+}
+
+/// Validate the parameters for a command against the protocol schema. Null params are
+/// treated as an empty object.
+pub fn validate_params(method: &str, params: &Value) -> Result<(), ValidationError> {
+    let cmd = command(method)
+        .ok_or_else(|| ValidationError::UnknownMethod(method.to_string()))?;
+    match *params {
+        Value::Null => validate_fields("", cmd.parameters, &Default::default()),
+        Value::Object(ref obj) => validate_fields("", cmd.parameters, obj),
+        _ => Err(ValidationError::InvalidParams),
+    }
+}
+
+fn validate_fields(prefix: &str, fields: &[ParamInfo], obj: &::serde_json::Map<String, Value>) -> Result<(), ValidationError> {
+    for key in obj.keys() {
+        if !fields.iter().any(|f| f.name == key) {
+            return Err(ValidationError::UnknownParam(format!("{}{}", prefix, key)));
+        }
+    }
+
+    for field in fields {
+        let path = format!("{}{}", prefix, field.name);
+        match obj.get(field.name) {
+            None | Some(&Value::Null) if field.optional => (),
+            None => return Err(ValidationError::MissingParam(path)),
+            Some(v) => validate_value(&path, field.ty, field.items, field.enum_values, v)?,
+        }
+    }
+    Ok(())
+}
+
+fn validate_value(path: &str, ty: &str, items: Option<&str>, enum_values: &[&str], v: &Value) -> Result<(), ValidationError> {
+    let valid = match ty {
+        "string" => {
+            if let Some(s) = v.as_str() {
+                if !enum_values.is_empty() && !enum_values.contains(&s) {
+                    return Err(ValidationError::InvalidValue { param: path.to_string(), value: s.to_string() });
+                }
+            }
+            v.is_string()
+        }
+        "integer" => v.is_i64() || v.is_u64(),
+        "number" => v.is_number(),
+        "boolean" => v.is_boolean(),
+        "object" => v.is_object(),
+        "any" => true,
+        "array" => {
+            if let (Some(values), Some(items)) = (v.as_array(), items) {
+                for (idx, item) in values.iter().enumerate() {
+                    validate_value(&format!("{}[{}]", path, idx), items, None, &[], item)?;
+                }
+            }
+            v.is_array()
+        }
+        reference => {
+            // Unknown references are not validated
+            return match type_info(reference) {
+                Some(t) if !t.properties.is_empty() => match *v {
+                    Value::Object(ref obj) => validate_fields(&format!("{}.", path), t.properties, obj),
+                    _ => Err(ValidationError::InvalidType { param: path.to_string(), expected: reference.to_string() }),
+                },
+                Some(t) => validate_value(path, t.ty, t.items, t.enum_values, v),
+                None => Ok(()),
+            };
+        }
+    };
+
+    if valid {
+        Ok(())
+    } else {
+        Err(ValidationError::InvalidType { param: path.to_string(), expected: ty.to_string() })
+    }
+}
+
+/// A difference between the bundled schema and the one reported by the browser
+#[derive(Debug, PartialEq)]
+pub enum Mismatch {
+    /// The browser does not support this domain
+    MissingDomain(&'static str),
+    /// The browser supports a domain that is not in the bundled schema
+    UnknownDomain(String),
+    /// The browser reports a different protocol version for this domain
+    Version { domain: String, version: String },
+}
+
+/// Compare the domains reported by `Schema.getDomains` against the bundled schema
+pub fn compare_domains(remote: &[proto::Schema::Domain]) -> Vec<Mismatch> {
+    let expected_version = format!("{}.{}", VERSION.0, VERSION.1);
+    let mut mismatches = Vec::new();
+
+    for d in DOMAINS {
+        if !remote.iter().any(|r| r._name == d.name) {
+            mismatches.push(Mismatch::MissingDomain(d.name));
+        }
+    }
+    for r in remote {
+        if domain(&r._name).is_none() {
+            mismatches.push(Mismatch::UnknownDomain(r._name.clone()));
+        } else if r._version != expected_version {
+            mismatches.push(Mismatch::Version { domain: r._name.clone(), version: r._version.clone() });
+        }
+    }
+    mismatches
+}
@@ -0,0 +1,143 @@
+//! Screencast frame stream
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use base64;
+
+use proto::{Event, Page, PageApi};
+use screenshot::ImageFormat;
+use {DebugClient, Error};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScreencastOptions {
+    pub format: ImageFormat,
+    pub max_width: Option<i64>,
+    pub max_height: Option<i64>,
+    /// Only send every n-th frame
+    pub every_nth_frame: Option<i64>,
+}
+
+impl Default for ScreencastOptions {
+    fn default() -> Self {
+        ScreencastOptions {
+            format: ImageFormat::Png,
+            max_width: None,
+            max_height: None,
+            every_nth_frame: None,
+        }
+    }
+}
+
+/// A decoded screencast frame
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScreencastFrame {
+    pub data: Vec<u8>,
+    pub metadata: Page::ScreencastFrameMetadata,
+}
+
+/// A running screencast, frames are acknowledged as they are read and the screencast
+/// is stopped when this is dropped
+pub struct Screencast<'a> {
+    client: &'a mut DebugClient,
+    format: ImageFormat,
+}
+
+/// Remove the first screencast frame from the event queue
+fn take_frame(events: &mut Vec<Event>) -> Option<(i64, String, Page::ScreencastFrameMetadata)> {
+    let idx = events.iter().position(|ev| matches!(*ev, Event::Page(Page::Event::screencastFrame { .. })))?;
+    match events.remove(idx) {
+        Event::Page(Page::Event::screencastFrame { sessionId, data, metadata }) => Some((sessionId, data, metadata)),
+        _ => unreachable!(),
+    }
+}
+
+impl<'a> Screencast<'a> {
+    /// Wait for the next frame, or until the client timeout expires. Frames are removed
+    /// from pending_events.
+    pub fn next_frame(&mut self) -> Result<ScreencastFrame, Error> {
+        let deadline = Instant::now() + self.client.timeout;
+        loop {
+            if let Some((session_id, data, metadata)) = take_frame(&mut self.client.pending_events) {
+                // Chrome stops sending frames until the previous one was acknowledged
+                PageApi::screencastFrameAck(self.client, session_id)?;
+                return Ok(ScreencastFrame {
+                    data: base64::decode(&data)?,
+                    metadata,
+                });
+            }
+            if !self.client.poll_until(deadline)? {
+                return Err(Error::Timeout);
+            }
+        }
+    }
+
+    /// Write the next `count` frames to `dir` as frame-00000.png, frame-00001.png, ...
+    pub fn save_frames<P: AsRef<Path>>(&mut self, dir: P, count: usize) -> Result<Vec<PathBuf>, Error> {
+        let ext = match self.format {
+            ImageFormat::Png => "png",
+            ImageFormat::Jpeg(_) => "jpg",
+        };
+        fs::create_dir_all(dir.as_ref())?;
+
+        let mut paths = Vec::new();
+        for i in 0..count {
+            let frame = self.next_frame()?;
+            let path = dir.as_ref().join(format!("frame-{:05}.{}", i, ext));
+            fs::write(&path, frame.data)?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+}
+
+impl<'a> Iterator for Screencast<'a> {
+    type Item = Result<ScreencastFrame, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.next_frame())
+    }
+}
+
+impl<'a> Drop for Screencast<'a> {
+    fn drop(&mut self) {
+        if let Err(e) = PageApi::stopScreencast(self.client) {
+            warn!("Failed to stop screencast: {:?}", e);
+        }
+    }
+}
+
+impl DebugClient {
+    /// Start a screencast, requires the Page domain to be enabled
+    pub fn screencast(&mut self, opts: &ScreencastOptions) -> Result<Screencast<'_>, Error> {
+        let (format, quality) = opts.format.params();
+        PageApi::startScreencast(self, format, quality, opts.max_width, opts.max_height, opts.every_nth_frame)?;
+        Ok(Screencast {
+            client: self,
+            format: opts.format,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn take_frames() {
+        let frame: Event = serde_json::from_str(r#"{"method": "Page.screencastFrame", "params": {
+            "data": "aGVsbG8=", "sessionId": 3, "metadata": {"offsetTop": 0, "pageScaleFactor": 1,
+            "deviceWidth": 800, "deviceHeight": 600, "scrollOffsetX": 0, "scrollOffsetY": 0}}}"#).unwrap();
+        let other: Event = serde_json::from_str(r#"{"method": "Page.loadEventFired", "params": {"timestamp": 1.0}}"#).unwrap();
+
+        let mut events = vec![other.clone(), frame, other.clone()];
+        let (session_id, data, metadata) = take_frame(&mut events).unwrap();
+        assert_eq!(session_id, 3);
+        assert_eq!(base64::decode(&data).unwrap(), b"hello");
+        assert_eq!(metadata._deviceWidth, 800.0);
+        assert_eq!(events, vec![other.clone(), other]);
+        assert_eq!(take_frame(&mut events), None);
+    }
+}
@@ -0,0 +1,188 @@
+//! Screenshot helpers
+
+use std::fs;
+use std::path::Path;
+
+use base64;
+use proto::{DOM, DOMApi, Emulation, EmulationApi, Nothing, Page, PageApi};
+use {DebugClient, Error};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImageFormat {
+    Png,
+    /// JPEG with a quality in the range [0..100], higher values are clamped
+    Jpeg(u8),
+}
+
+/// The part of the page to capture
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScreenshotArea {
+    /// The visible viewport
+    Viewport,
+    /// The whole page, including content outside the viewport
+    FullPage,
+    /// A rectangle in page coordinates
+    Clip(DOM::Rect),
+    /// The border box of a DOM node
+    Node(DOM::NodeId),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScreenshotOptions {
+    pub format: ImageFormat,
+    pub area: ScreenshotArea,
+    /// Capture from the surface rather than the view
+    pub from_surface: Option<bool>,
+}
+
+impl Default for ScreenshotOptions {
+    fn default() -> Self {
+        ScreenshotOptions {
+            format: ImageFormat::Png,
+            area: ScreenshotArea::Viewport,
+            from_surface: None,
+        }
+    }
+}
+
+impl ImageFormat {
+    pub(crate) fn params(&self) -> (Option<String>, Option<i64>) {
+        match *self {
+            ImageFormat::Png => (Some("png".to_string()), None),
+            ImageFormat::Jpeg(quality) => (Some("jpeg".to_string()), Some(i64::from(quality.min(100)))),
+        }
+    }
+}
+
+/// The bounding rectangle of a quad, i.e. x1 y1 x2 y2 x3 y3 x4 y4
+fn quad_bounds(quad: &DOM::Quad) -> DOM::Rect {
+    let xs = quad.iter().step_by(2);
+    let ys = quad.iter().skip(1).step_by(2);
+    let (min_x, max_x) = xs.fold((f64::MAX, f64::MIN), |(lo, hi), &x| (lo.min(x), hi.max(x)));
+    let (min_y, max_y) = ys.fold((f64::MAX, f64::MIN), |(lo, hi), &y| (lo.min(y), hi.max(y)));
+    DOM::Rect {
+        _x: min_x,
+        _y: min_y,
+        _width: max_x - min_x,
+        _height: max_y - min_y,
+    }
+}
+
+fn fits_viewport(clip: &DOM::Rect, viewport: &Page::LayoutViewport) -> bool {
+    clip._x >= viewport._pageX as f64 && clip._y >= viewport._pageY as f64
+        && clip._x + clip._width <= (viewport._pageX + viewport._clientWidth) as f64
+        && clip._y + clip._height <= (viewport._pageY + viewport._clientHeight) as f64
+}
+
+impl DebugClient {
+    /// Override the device metrics. Unlike EmulationApi::setDeviceMetricsOverride the
+    /// override is remembered, the browser has no command to query it, so screenshot can
+    /// restore it.
+    pub fn set_device_metrics(&mut self, params: Emulation::Request_setDeviceMetricsOverride) -> Result<(), Error> {
+        self.call::<_, Nothing>("Emulation.setDeviceMetricsOverride", &params)?;
+        self.device_metrics = Some(params);
+        Ok(())
+    }
+
+    /// Clear the override from set_device_metrics
+    pub fn clear_device_metrics(&mut self) -> Result<(), Error> {
+        EmulationApi::clearDeviceMetricsOverride(self)?;
+        self.device_metrics = None;
+        Ok(())
+    }
+
+    /// Capture a screenshot and return the decoded image.
+    ///
+    /// When the area does not fit the viewport, the device metrics are overridden to the
+    /// size of the page during the capture, keeping the device scale factor. Afterwards the
+    /// previous override from set_device_metrics is restored, or cleared if there was none.
+    pub fn screenshot(&mut self, opts: &ScreenshotOptions) -> Result<Vec<u8>, Error> {
+        let (format, quality) = opts.format.params();
+        let metrics = PageApi::getLayoutMetrics(self)?;
+
+        let clip = match opts.area {
+            ScreenshotArea::Viewport => None,
+            ScreenshotArea::FullPage => Some(DOM::Rect {
+                _x: 0.0,
+                _y: 0.0,
+                _width: metrics.contentSize._width.ceil(),
+                _height: metrics.contentSize._height.ceil(),
+            }),
+            ScreenshotArea::Clip(ref rect) => Some(rect.clone()),
+            ScreenshotArea::Node(node) => {
+                let model = DOMApi::getBoxModel(self, Some(node), None, None)?.model;
+                // Box model quads are relative to the viewport
+                let mut rect = quad_bounds(&model._border);
+                rect._x += metrics.layoutViewport._pageX as f64;
+                rect._y += metrics.layoutViewport._pageY as f64;
+                Some(rect)
+            }
+        };
+
+        let resize = match clip {
+            Some(ref clip) => !fits_viewport(clip, &metrics.layoutViewport),
+            None => false,
+        };
+        let previous = self.device_metrics.clone();
+        if resize {
+            let width = metrics.contentSize._width.ceil() as i64;
+            let height = metrics.contentSize._height.ceil() as i64;
+            // This includes the scale factor of a previous override
+            let scale_factor = self.eval::<f64>("window.devicePixelRatio")?;
+            let mobile = previous.as_ref().is_some_and(|p| p._mobile);
+            EmulationApi::setDeviceMetricsOverride(self, width, height, scale_factor, mobile,
+                None, None, None, None, None, None, None)?;
+        }
+
+        let clip = clip.map(|rect| Page::Viewport {
+            _x: rect._x,
+            _y: rect._y,
+            _width: rect._width,
+            _height: rect._height,
+            _scale: 1.0,
+        });
+        let r = PageApi::captureScreenshot(self, format, quality, clip, opts.from_surface);
+
+        // Restore the viewport even if the capture failed
+        if resize {
+            match previous {
+                Some(params) => self.call::<_, Nothing>("Emulation.setDeviceMetricsOverride", params).map(|_| ())?,
+                None => EmulationApi::clearDeviceMetricsOverride(self).map(|_| ())?,
+            }
+        }
+        Ok(base64::decode(&r?.data)?)
+    }
+
+    /// Capture a screenshot and write it to a file
+    pub fn screenshot_to_file<P: AsRef<Path>>(&mut self, opts: &ScreenshotOptions, path: P) -> Result<(), Error> {
+        let data = self.screenshot(opts)?;
+        fs::write(path, data)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounds() {
+        let quad = vec![10.0, 20.0, 110.0, 20.0, 110.0, 70.0, 10.0, 70.0];
+        assert_eq!(quad_bounds(&quad), DOM::Rect { _x: 10.0, _y: 20.0, _width: 100.0, _height: 50.0 });
+    }
+
+    #[test]
+    fn viewport() {
+        let viewport = Page::LayoutViewport { _pageX: 0, _pageY: 100, _clientWidth: 800, _clientHeight: 600 };
+        assert!(fits_viewport(&DOM::Rect { _x: 0.0, _y: 100.0, _width: 800.0, _height: 600.0 }, &viewport));
+        assert!(!fits_viewport(&DOM::Rect { _x: 0.0, _y: 0.0, _width: 10.0, _height: 10.0 }, &viewport));
+        assert!(!fits_viewport(&DOM::Rect { _x: 0.0, _y: 650.0, _width: 10.0, _height: 100.0 }, &viewport));
+    }
+
+    #[test]
+    fn format_params() {
+        assert_eq!(ImageFormat::Png.params(), (Some("png".to_string()), None));
+        assert_eq!(ImageFormat::Jpeg(80).params(), (Some("jpeg".to_string()), Some(80)));
+        assert_eq!(ImageFormat::Jpeg(200).params(), (Some("jpeg".to_string()), Some(100)));
+    }
+}
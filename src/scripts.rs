@@ -0,0 +1,171 @@
+//! Scripts injected into new documents
+
+use std::fs;
+use std::mem;
+use std::path::Path;
+
+use proto::{Page, PageApi};
+use remote::{Release, ReleaseQueue};
+use {DebugClient, Error};
+
+/// The parameters for Page.addScriptToEvaluateOnNewDocument, the bundled protocol lacks
+/// the world name
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct AddScript<'a> {
+    source: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    world_name: Option<&'a str>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct InjectedScript {
+    name: String,
+    source: String,
+    world: Option<String>,
+    identifier: Page::ScriptIdentifier,
+}
+
+/// Named scripts that run in every new document before the page scripts. The scripts
+/// are removed when the injector is dropped, with the next command sent by the client.
+#[derive(Debug)]
+pub struct ScriptInjector {
+    scripts: Vec<InjectedScript>,
+    releases: ReleaseQueue,
+}
+
+impl ScriptInjector {
+    /// Add a script to the main world, a script with the same name is replaced
+    pub fn add(&mut self, client: &mut DebugClient, name: &str, source: &str) -> Result<(), Error> {
+        self.insert(client, name, source.to_string(), None)
+    }
+
+    /// Add a script to an isolated world, which is created in each new document
+    pub fn add_in_world(&mut self, client: &mut DebugClient, name: &str, source: &str, world: &str) -> Result<(), Error> {
+        self.insert(client, name, source.to_string(), Some(world.to_string()))
+    }
+
+    /// Add a script from a file to the main world
+    pub fn add_file<P: AsRef<Path>>(&mut self, client: &mut DebugClient, name: &str, path: P) -> Result<(), Error> {
+        let source = fs::read_to_string(path)?;
+        self.insert(client, name, source, None)
+    }
+
+    fn insert(&mut self, client: &mut DebugClient, name: &str, source: String, world: Option<String>) -> Result<(), Error> {
+        // Register first, the old script is kept if this fails
+        let identifier = register(client, &source, world.as_deref())?;
+        let script = InjectedScript { name: name.to_string(), source, world, identifier };
+        match self.scripts.iter_mut().find(|s| s.name == name) {
+            Some(old) => {
+                let old = mem::replace(old, script);
+                PageApi::removeScriptToEvaluateOnNewDocument(client, old.identifier)?;
+            }
+            None => self.scripts.push(script),
+        }
+        Ok(())
+    }
+
+    /// Remove a script, returns false if there was none with that name
+    pub fn remove(&mut self, client: &mut DebugClient, name: &str) -> Result<bool, Error> {
+        let script = match self.scripts.iter().position(|s| s.name == name) {
+            Some(i) => self.scripts.remove(i),
+            None => return Ok(false),
+        };
+        PageApi::removeScriptToEvaluateOnNewDocument(client, script.identifier)?;
+        Ok(true)
+    }
+
+    /// Remove all scripts. On an error the scripts that were not removed are kept.
+    pub fn clear(&mut self, client: &mut DebugClient) -> Result<(), Error> {
+        while let Some(script) = self.scripts.first() {
+            PageApi::removeScriptToEvaluateOnNewDocument(client, script.identifier.clone())?;
+            self.scripts.remove(0);
+        }
+        Ok(())
+    }
+
+    /// The script names, in the order they were added
+    pub fn names(&self) -> Vec<&str> {
+        self.scripts.iter().map(|s| s.name.as_str()).collect()
+    }
+
+    /// The identifier the browser assigned to a script
+    pub fn identifier(&self, name: &str) -> Option<&Page::ScriptIdentifier> {
+        self.scripts.iter().find(|s| s.name == name).map(|s| &s.identifier)
+    }
+
+    /// Register all scripts again with a new client, e.g. after reconnecting to the
+    /// browser. The scripts are then removed through the new client.
+    pub fn reregister(&mut self, client: &mut DebugClient) -> Result<(), Error> {
+        self.releases = client.releases.clone();
+        for script in &mut self.scripts {
+            script.identifier = register(client, &script.source, script.world.as_deref())?;
+        }
+        Ok(())
+    }
+}
+
+fn register(client: &mut DebugClient, source: &str, world: Option<&str>) -> Result<Page::ScriptIdentifier, Error> {
+    let r: Page::ReturnType_addScriptToEvaluateOnNewDocument =
+        client.call("Page.addScriptToEvaluateOnNewDocument", AddScript { source, world_name: world })?;
+    Ok(r.identifier)
+}
+
+impl Drop for ScriptInjector {
+    fn drop(&mut self) {
+        if let Ok(mut releases) = self.releases.lock() {
+            releases.extend(self.scripts.drain(..).map(|s| Release::Script(s.identifier)));
+        }
+    }
+}
+
+impl DebugClient {
+    /// Create an empty script injector
+    pub fn script_injector(&mut self) -> ScriptInjector {
+        ScriptInjector {
+            scripts: Vec::new(),
+            releases: self.releases.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    fn script(name: &str, id: &str) -> InjectedScript {
+        InjectedScript {
+            name: name.to_string(),
+            source: "1".to_string(),
+            world: None,
+            identifier: Page::ScriptIdentifier(id.to_string()),
+        }
+    }
+
+    #[test]
+    fn remove_on_drop() {
+        let releases = ReleaseQueue::default();
+        let injector = ScriptInjector {
+            scripts: vec![script("a", "1"), script("b", "2")],
+            releases: releases.clone(),
+        };
+        assert_eq!(injector.names(), vec!["a", "b"]);
+        assert_eq!(injector.identifier("b"), Some(&Page::ScriptIdentifier("2".to_string())));
+        assert_eq!(injector.identifier("c"), None);
+        drop(injector);
+
+        assert_eq!(*releases.lock().unwrap(), vec![
+            Release::Script(Page::ScriptIdentifier("1".to_string())),
+            Release::Script(Page::ScriptIdentifier("2".to_string())),
+        ]);
+    }
+
+    #[test]
+    fn params() {
+        let main = AddScript { source: "x()", world_name: None };
+        assert_eq!(serde_json::to_string(&main).unwrap(), r#"{"source":"x()"}"#);
+        let isolated = AddScript { source: "x()", world_name: Some("tools") };
+        assert_eq!(serde_json::to_string(&isolated).unwrap(), r#"{"source":"x()","worldName":"tools"}"#);
+    }
+}
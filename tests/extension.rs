@@ -0,0 +1,39 @@
+//! Compile the protocol extension snapshot from codegen/fixtures as an external crate would
+
+extern crate cxdbg;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+
+#[allow(non_snake_case, non_camel_case_types, dead_code)]
+mod foo {
+    include!("../codegen/fixtures/extension_proto.rs");
+}
+
+#[test]
+fn extension_events() {
+    let ev: cxdbg::proto::Event = serde_json::from_str(
+        r#"{"method": "Foo.widgetAdded", "params": {"widget": {"name": "w", "object": "1.2"}}}"#).unwrap();
+    assert_eq!(ev.unknown_method(), Some("Foo.widgetAdded"));
+
+    match foo::Event::from_unknown(&ev) {
+        Some(foo::Event::Foo(foo::Foo::Event::widgetAdded { widget })) => {
+            assert_eq!(widget._name, "w");
+            assert_eq!(widget._object, Some(cxdbg::proto::Runtime::RemoteObjectId("1.2".to_string())));
+        }
+        ev => panic!("Unexpected event {:?}", ev),
+    }
+
+    let ev: cxdbg::proto::Event = serde_json::from_str(r#"{"method": "Page.frameResized"}"#).unwrap();
+    assert!(foo::Event::from_unknown(&ev).is_none());
+}
+
+#[test]
+fn extension_commands() {
+    let cmd = foo::Command::Foo(foo::Foo::Command::bar(foo::Foo::Request_bar {
+        _nodeId: cxdbg::proto::DOM::NodeId(1),
+    }));
+    assert_eq!(cmd.method_name(), "Foo.bar");
+    let _: fn(&mut cxdbg::DebugClient, cxdbg::proto::DOM::NodeId) -> Result<_, cxdbg::Error> = foo::FooApi::bar;
+}